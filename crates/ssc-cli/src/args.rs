@@ -0,0 +1,176 @@
+use ssc_core::peer::sas::SasFormat;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage:
+  ssc-cli offer  [options]    create an offer, then read the answer
+  ssc-cli accept [options]    read an offer, then print the answer
+
+Options:
+  --input FILE               read the peer's offer/answer from FILE instead of stdin
+  --passphrase TEXT          protect the exchange with a shared passphrase (CPace)
+  --transport-code           offer: encrypt the offer with a new transport code
+  --code CODE                accept: the transport code shown by the peer
+  --sas hex|words|emoji|numeric
+                             how to show the verification code (default: hex)
+  --data-dir DIR             where to keep the identity key and pinned contacts
+  --identity                 sign the session with the identity key (needs --data-dir)
+  --no-qr                    do not print the offer/answer as a QR code
+  -h, --help                 show this help
+
+The offer/answer is printed to stdout, everything else goes to stderr.
+Log level is taken from RUST_LOG (default: warn).";
+
+/// Какую сторону обмена играет клиент
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Создаёт offer и ждёт answer
+    Offer,
+    /// Принимает offer и отдаёт answer
+    Accept,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub mode: Mode,
+    /// Файл с пакетом собеседника, иначе stdin
+    pub input: Option<PathBuf>,
+    pub passphrase: Option<String>,
+    /// Для offer - сгенерировать код переноса
+    pub generate_transport_code: bool,
+    /// Для accept - код переноса, показанный собеседником
+    pub transport_code: Option<String>,
+    pub sas_format: SasFormat,
+    pub data_dir: Option<PathBuf>,
+    pub identity: bool,
+    pub qr: bool,
+}
+
+/// Разбор аргументов; `Ok(None)` - запрошена справка
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter().peekable();
+    let mode = match args.next().as_deref() {
+        Some("offer") => Mode::Offer,
+        Some("accept") => Mode::Accept,
+        Some("-h" | "--help") => return Ok(None),
+        Some(other) => return Err(format!("unknown command {:?}", other)),
+        None => return Err("missing command".into()),
+    };
+
+    let mut options = Options {
+        mode,
+        input: None,
+        passphrase: None,
+        generate_transport_code: false,
+        transport_code: None,
+        sas_format: SasFormat::Hex,
+        data_dir: None,
+        identity: false,
+        qr: true,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "--input" => options.input = Some(value("--input")?.into()),
+            "--passphrase" => options.passphrase = Some(value("--passphrase")?),
+            "--transport-code" if mode == Mode::Offer => options.generate_transport_code = true,
+            "--code" if mode == Mode::Accept => options.transport_code = Some(value("--code")?),
+            "--transport-code" => {
+                return Err("--transport-code is for offer; use --code CODE to accept".into())
+            }
+            "--code" => return Err("--code is for accept; use --transport-code to offer".into()),
+            "--sas" => options.sas_format = parse_sas_format(&value("--sas")?)?,
+            "--data-dir" => options.data_dir = Some(value("--data-dir")?.into()),
+            "--identity" => options.identity = true,
+            "--no-qr" => options.qr = false,
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown option {:?}", other)),
+        }
+    }
+
+    if options.identity && options.data_dir.is_none() {
+        return Err("--identity requires --data-dir".into());
+    }
+    Ok(Some(options))
+}
+
+fn parse_sas_format(value: &str) -> Result<SasFormat, String> {
+    match value {
+        "hex" => Ok(SasFormat::Hex),
+        "words" => Ok(SasFormat::Words),
+        "emoji" => Ok(SasFormat::Emoji),
+        "numeric" => Ok(SasFormat::Numeric),
+        other => Err(format!("unknown SAS format {:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Option<Options>, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn offer_with_transport_code() {
+        let options = parse_args(&["offer", "--transport-code", "--sas", "words"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.mode, Mode::Offer);
+        assert!(options.generate_transport_code);
+        assert_eq!(options.transport_code, None);
+        assert_eq!(options.sas_format, SasFormat::Words);
+        assert!(options.qr);
+    }
+
+    #[test]
+    fn accept_with_code() {
+        let options = parse_args(&["accept", "--code", "ABCD-1234", "--no-qr"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.mode, Mode::Accept);
+        assert!(!options.generate_transport_code);
+        assert_eq!(options.transport_code.as_deref(), Some("ABCD-1234"));
+        assert!(!options.qr);
+    }
+
+    #[test]
+    fn defaults_without_options() {
+        for (command, mode) in [("offer", Mode::Offer), ("accept", Mode::Accept)] {
+            let options = parse_args(&[command]).unwrap().unwrap();
+            assert_eq!(options.mode, mode);
+            assert!(!options.generate_transport_code);
+            assert_eq!(options.transport_code, None);
+            assert_eq!(options.passphrase, None);
+            assert_eq!(options.sas_format, SasFormat::Hex);
+        }
+    }
+
+    #[test]
+    fn transport_code_flags_belong_to_their_mode() {
+        assert!(parse_args(&["accept", "--transport-code", "ABCD"]).is_err());
+        assert!(parse_args(&["offer", "--code", "ABCD"]).is_err());
+    }
+
+    #[test]
+    fn missing_values_and_unknown_arguments_are_errors() {
+        assert!(parse_args(&["accept", "--code"]).is_err());
+        assert!(parse_args(&["offer", "--passphrase"]).is_err());
+        assert!(parse_args(&["offer", "--sas", "braille"]).is_err());
+        assert!(parse_args(&["offer", "--bogus"]).is_err());
+        assert!(parse_args(&["answer"]).is_err());
+        assert!(parse_args(&[]).is_err());
+        assert!(parse_args(&["offer", "--identity"]).is_err());
+    }
+
+    #[test]
+    fn help_is_not_an_error() {
+        assert!(parse_args(&["--help"]).unwrap().is_none());
+        assert!(parse_args(&["accept", "-h"]).unwrap().is_none());
+    }
+}
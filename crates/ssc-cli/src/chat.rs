@@ -0,0 +1,172 @@
+use ssc_core::peer::identity::IdentityState;
+use ssc_core::peer::sas::SasFormat;
+use ssc_core::peer::types::SendStatus;
+use ssc_core::{Client, Event, SscError};
+use std::error::Error;
+use std::time::Duration;
+use tokio::io::{BufReader, Lines, Stdin};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::timeout;
+use tracing::debug;
+
+/// Строки, введённые пользователем
+pub type Input = Lines<BufReader<Stdin>>;
+
+/// Сколько ждать установки соединения после обмена offer/answer
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Команда выхода из чата
+const QUIT: &str = "/quit";
+
+/// Ждёт согласования ключей в сессии `session_id`
+pub async fn wait_connected(
+    events: &mut broadcast::Receiver<Event>,
+    session_id: &str,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!("* Connecting...");
+    timeout(CONNECT_TIMEOUT, next_connected(events, session_id))
+        .await
+        .map_err(|_| {
+            SscError::Timeout(format!(
+                "no connection within {} s",
+                CONNECT_TIMEOUT.as_secs()
+            ))
+        })?
+}
+
+async fn next_connected(
+    events: &mut broadcast::Receiver<Event>,
+    session_id: &str,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Err("event stream closed".into()),
+        };
+        if event.session_id().is_some_and(|id| id != session_id) {
+            continue;
+        }
+        debug!("Event while connecting: {:?}", event);
+        match event {
+            Event::Connected { .. } => return Ok(()),
+            Event::IdentityChanged { details, .. } => eprintln!(
+                "! Identity key of contact {} changed: {} -> {}",
+                details.installation_id, details.previous_fingerprint, details.new_fingerprint
+            ),
+            Event::IncompatiblePeer { details, .. } if details.remote_versions.is_empty() => {
+                return Err("peer runs an older version without protocol negotiation".into())
+            }
+            Event::IncompatiblePeer { details, .. } => {
+                return Err(format!(
+                    "peer is incompatible: versions {:?}, suites {:?}",
+                    details.remote_versions, details.remote_suites
+                )
+                .into())
+            }
+            Event::KeyConfirmationFailed { .. } => {
+                return Err("key confirmation failed, the peer derived different keys".into())
+            }
+            Event::ConnectionFailed { .. } | Event::Disconnected { .. } => {
+                return Err("connection failed".into())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Показывает SAS и спрашивает, совпадает ли он у собеседника
+pub async fn verify(
+    client: &Client,
+    session_id: &str,
+    format: SasFormat,
+    input: &mut Input,
+) -> Result<bool, Box<dyn Error>> {
+    let sas = client
+        .fingerprint_as(session_id, format)?
+        .ok_or(SscError::NoSession)?;
+    if let Some(peer) = client.peer_identity(session_id)? {
+        eprintln!("* Peer identity: {}", peer);
+    }
+    match client.identity_state(session_id)? {
+        Some(IdentityState::Unknown) => {
+            eprintln!("* This identity was never verified, compare the code carefully")
+        }
+        Some(IdentityState::Changed) => {
+            eprintln!("! This contact used a different identity before")
+        }
+        Some(IdentityState::Verified) | None => {}
+    }
+    eprintln!("* Verification code: {}", sas);
+    eprint!("Does it match the code on the peer's screen? [y/N] ");
+
+    let answer = input.next_line().await?.unwrap_or_default();
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        return Ok(false);
+    }
+    client.mark_verified(session_id)?;
+    Ok(true)
+}
+
+/// Построчный чат до `/quit`, конца ввода или отключения собеседника
+pub async fn run(
+    client: &Client,
+    mut events: broadcast::Receiver<Event>,
+    session_id: &str,
+    input: &mut Input,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!(
+        "* Secure channel ready. Type a message and press Enter, {} to leave.",
+        QUIT
+    );
+    loop {
+        tokio::select! {
+            line = input.next_line() => {
+                let Some(line) = line? else { break };
+                if line.trim() == QUIT {
+                    break;
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match client.send(session_id, &line).await {
+                    Ok(SendStatus::Sent) => {}
+                    Ok(SendStatus::Queued) => eprintln!("* Queued until keys are renewed"),
+                    Err(e) => eprintln!("! Not sent: {}", e),
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        eprintln!("! {} events missed", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if event.session_id().is_some_and(|id| id != session_id) {
+                    continue;
+                }
+                match event {
+                    Event::Message { text, .. } => println!("peer> {}", text),
+                    Event::NonceExhausted { .. } => eprintln!("* Renewing keys, messages will be sent afterwards"),
+                    Event::RekeyFailed { .. } => return Err("peer did not answer key renewal".into()),
+                    Event::ConnectionProblem { .. } => eprintln!("* Connection problem, recovering..."),
+                    Event::ConnectionRecovered { .. } => eprintln!("* Connection restored"),
+                    Event::ConnectionFailed { .. } => return Err("connection lost".into()),
+                    Event::Disconnected { .. } => {
+                        eprintln!("* Peer disconnected");
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Сессия уже могла закрыться со стороны собеседника
+    if let Err(e) = client.close_session(session_id).await {
+        debug!("Session already closed: {}", e);
+    }
+    Ok(())
+}
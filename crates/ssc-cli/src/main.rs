@@ -0,0 +1,138 @@
+mod args;
+mod chat;
+mod qr;
+
+use args::{Mode, Options, USAGE};
+use chat::Input;
+use ssc_core::peer::signaling::generate_transport_code;
+use ssc_core::{settings, Client};
+use std::error::Error;
+use std::path::Path;
+use std::process::ExitCode;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing_subscriber::EnvFilter;
+
+/// Фильтр логов по умолчанию: в терминале только предупреждения, чтобы не мешать чату
+const DEFAULT_LOG_FILTER: &str = "warn";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match args::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("ssc-cli: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER)),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    match run(options).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ssc-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = options.data_dir.clone() {
+        settings::set_data_dir(dir);
+    }
+    if options.identity {
+        settings::set_identity_enabled(true)?;
+    }
+    // В терминале SAS сверяется всегда, до первого сообщения
+    settings::set_require_verification(true);
+
+    let client = Client::new();
+    let mut events = client.subscribe();
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+
+    let session_id = match options.mode {
+        Mode::Offer => {
+            let transport_code = options
+                .generate_transport_code
+                .then(generate_transport_code);
+            let offer = client
+                .create_offer(options.passphrase.clone(), transport_code.clone())
+                .await?;
+            print_bundle("offer", &offer.encoded, options.qr);
+            if let Some(code) = &transport_code {
+                eprintln!("* Transport code (the peer passes it as --code): {}", code);
+            }
+
+            let answer = read_bundle("answer", options.input.as_deref(), &mut input).await?;
+            client.apply_answer(&offer.session_id, &answer).await?;
+            offer.session_id
+        }
+        Mode::Accept => {
+            let offer = read_bundle("offer", options.input.as_deref(), &mut input).await?;
+            let answer = client
+                .accept_offer(
+                    &offer,
+                    options.passphrase.clone(),
+                    options.transport_code.clone(),
+                )
+                .await?;
+            print_bundle("answer", &answer.encoded, options.qr);
+            eprintln!("* Send the answer to the peer");
+            answer.session_id
+        }
+    };
+
+    chat::wait_connected(&mut events, &session_id).await?;
+    if !chat::verify(&client, &session_id, options.sas_format, &mut input).await? {
+        eprintln!("* Verification code rejected, closing the session");
+        client.reject(&session_id).await?;
+        return Ok(());
+    }
+    chat::run(&client, events, &session_id, &mut input).await
+}
+
+/// Пакет в stdout (для копирования или перенаправления в файл), QR-код - в stderr
+fn print_bundle(kind: &str, encoded: &str, qr: bool) {
+    if qr {
+        match qr::render(encoded) {
+            Some(code) => eprintln!("{}", code),
+            None => eprintln!(
+                "! The {} is too large for a QR code, copy the text below",
+                kind
+            ),
+        }
+    }
+    eprintln!("* Your {}:", kind);
+    println!("{}", encoded);
+}
+
+/// Пакет собеседника из файла или первой непустой строки stdin
+async fn read_bundle(
+    kind: &str,
+    path: Option<&Path>,
+    input: &mut Input,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(path) = path {
+        let encoded = tokio::fs::read_to_string(path).await?;
+        return Ok(encoded.trim().to_string());
+    }
+
+    eprintln!("* Paste the peer's {} and press Enter:", kind);
+    while let Some(line) = input.next_line().await? {
+        let line = line.trim();
+        if !line.is_empty() {
+            return Ok(line.to_string());
+        }
+    }
+    Err(format!("no {} on stdin", kind).into())
+}
@@ -0,0 +1,18 @@
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use tracing::warn;
+
+/// QR-код для терминала: два модуля на символ, цвета инвертированы под тёмный фон
+///
+/// `None`, если пакет не помещается в QR-код (много ICE кандидатов).
+pub fn render(data: &str) -> Option<String> {
+    let code = QrCode::new(data.as_bytes())
+        .inspect_err(|e| warn!("Bundle does not fit into a QR code: {}", e))
+        .ok()?;
+    Some(
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build(),
+    )
+}
@@ -0,0 +1,720 @@
+use crate::error::SscError;
+use crate::events::{emit_nonce_exhausted, Event};
+use crate::logger::Plaintext;
+use crate::peer::connection::new_peer;
+use crate::peer::crypto::SealError;
+use crate::peer::data_channel::{queue_until_rekey, send_frame, start_rekey, MessageType};
+use crate::peer::ice::{self, analyze_candidates, apply_pending_candidates, wait_for_candidates};
+use crate::peer::identity::{pin_verified, IdentityState};
+use crate::peer::pake::{normalize_passphrase, parse_message, Pake, PakeRole};
+use crate::peer::sas::SasFormat;
+use crate::peer::session::{Session, SessionInfo, SessionManager};
+use crate::peer::signaling::{
+    check_freshness, check_pending_offer, mark_offer_used, open_signed_bundle, seal_bundle,
+    unmark_offer_used, BundleSigner,
+};
+use crate::peer::state::REQUIRE_VERIFICATION;
+use crate::peer::traffic::{frame, framing_enabled, restart_cover_traffic, InnerKind};
+use crate::peer::types::{ConnectionBundle, IceCandidate, SdpPayload, SendStatus, SignalingBundle};
+use crate::settings;
+use crate::utils::random_id;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer_connection::RTCPeerConnection;
+
+/// Клиент ssc: сессии, обмен offer/answer, сообщения и поток событий
+///
+/// Не зависит от UI: Tauri, консольный клиент и тесты работают с ним одинаково.
+/// Глобальные настройки (padding, TTL, ключи идентичности) - в `settings`.
+pub struct Client {
+    sessions: Arc<SessionManager>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self {
+            sessions: SessionManager::new(),
+        }
+    }
+
+    /// Поток событий всех сессий клиента
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sessions.events.subscribe()
+    }
+
+    pub fn session(&self, session_id: &str) -> Result<Arc<Session>, SscError> {
+        self.sessions.get(session_id)
+    }
+
+    /// Список разговоров с их состоянием
+    pub fn list_sessions(&self) -> Vec<SessionInfo> {
+        self.sessions.list()
+    }
+
+    /// Делает разговор активным в UI
+    pub fn switch_session(&self, session_id: &str) -> Result<(), SscError> {
+        self.sessions.set_active(session_id)?;
+        info!("Switched to session {}", session_id);
+        Ok(())
+    }
+
+    /// Закрывает один разговор, остальные продолжают работать
+    pub async fn close_session(&self, session_id: &str) -> Result<(), SscError> {
+        self.sessions.get(session_id)?.close().await;
+        Ok(())
+    }
+
+    /// Offer с полным набором ICE кандидатов
+    ///
+    /// С паролем offer несёт сообщение CPace: ответить на него сможет только
+    /// знающий пароль, а ключи сессии будут привязаны к результату обмена.
+    /// С кодом переноса offer шифруется целиком, answer ожидается зашифрованным тем же кодом.
+    /// Каждый offer открывает новую сессию, её id возвращается вместе с пакетом.
+    pub async fn create_offer(
+        &self,
+        passphrase: Option<String>,
+        transport_code: Option<String>,
+    ) -> Result<SignalingBundle, SscError> {
+        debug!("create_offer called");
+
+        // Сессия видна в списке уже во время сбора кандидатов
+        let session = self.sessions.open(random_id())?;
+
+        let result = offer_with_candidates(&session, passphrase, transport_code).await;
+        bundle_or_close(session, result).await
+    }
+
+    /// Принятие offer с полным набором ICE кандидатов, возвращает answer
+    ///
+    /// Сессия получает id из offer, так что у обеих сторон он совпадает.
+    pub async fn accept_offer(
+        &self,
+        encoded: &str,
+        passphrase: Option<String>,
+        transport_code: Option<String>,
+    ) -> Result<SignalingBundle, SscError> {
+        debug!("accept_offer called");
+
+        // Декодируем bundle, проверяем подпись, MAC, свежесть и однократность до создания соединения
+        let (bundle, signer) = open_signed_bundle::<ConnectionBundle>(
+            encoded,
+            transport_code.as_deref(),
+            &self.sessions.events,
+            None,
+        )
+        .and_then(|(bundle, signer)| {
+            check_freshness(bundle.sdp_payload.ts)?;
+            mark_offer_used(&bundle.sdp_payload.id)?;
+            Ok((bundle, signer))
+        })
+        .inspect_err(|e| warn!("Rejected offer bundle: {}", e))?;
+
+        // Offer отмечен принятым сразу, чтобы параллельная попытка с ним же не прошла;
+        // если ответить не удалось, его можно принять снова (например, с паролем)
+        let offer_id = bundle.sdp_payload.id.clone();
+        let result = self
+            .answer_offer(bundle, signer, passphrase, transport_code)
+            .await;
+        if result.is_err() {
+            unmark_offer_used(&offer_id);
+        }
+        result
+    }
+
+    /// Answer на проверенный offer: CPace, новая сессия и сбор кандидатов
+    async fn answer_offer(
+        &self,
+        bundle: ConnectionBundle,
+        signer: BundleSigner,
+        passphrase: Option<String>,
+        transport_code: Option<String>,
+    ) -> Result<SignalingBundle, SscError> {
+        // Завершаем CPace до того, как открывать сессию
+        let (pake_key, pake_message) =
+            match (bundle.pake.as_deref(), normalize_passphrase(passphrase)) {
+                (Some(offer_message), Some(passphrase)) => {
+                    let offer_message = parse_message(offer_message)
+                        .ok_or_else(|| SscError::Pake("malformed PAKE message".into()))?;
+                    let pake =
+                        Pake::start(PakeRole::Responder, &passphrase, &bundle.sdp_payload.id);
+                    let message = pake.message();
+                    let key = pake
+                        .finish(&offer_message)
+                        .ok_or_else(|| SscError::Pake("invalid PAKE message".into()))?;
+                    info!("Offer passphrase exchange (CPace) completed");
+                    (Some(key), Some(hex::encode(message)))
+                }
+                (Some(_), None) => {
+                    warn!("Rejected offer: it is protected by a passphrase");
+                    return Err(SscError::Pake("offer requires a passphrase".into()));
+                }
+                (None, Some(_)) => {
+                    warn!("Rejected offer: passphrase given but offer is not protected");
+                    return Err(SscError::Pake("offer is not passphrase-protected".into()));
+                }
+                (None, None) => (None, None),
+            };
+
+        let session = self.sessions.open(bundle.sdp_payload.id.clone())?;
+        *session.pake_key.lock().unwrap() = pake_key;
+        remember_signer(&session, signer);
+        let result = answer_with_candidates(&session, bundle, pake_message, transport_code).await;
+        bundle_or_close(session, result).await
+    }
+
+    /// Применение answer с полным набором ICE кандидатов к сессии `session_id`
+    pub async fn apply_answer(&self, session_id: &str, encoded: &str) -> Result<(), SscError> {
+        debug!("apply_answer called for {}", session_id);
+        let session = self.sessions.get(session_id)?;
+
+        // Декодируем bundle и проверяем подпись, MAC, свежесть и id до применения к соединению
+        let transport_code = session.transport_code.lock().unwrap().clone();
+        let (bundle, signer) = open_signed_bundle::<ConnectionBundle>(
+            encoded,
+            transport_code.as_deref(),
+            &session.events,
+            Some(session_id),
+        )
+        .and_then(|(bundle, signer)| {
+            check_freshness(bundle.sdp_payload.ts)?;
+            check_pending_offer(&session, &bundle.sdp_payload.id)?;
+            Ok((bundle, signer))
+        })
+        .inspect_err(|e| warn!("Rejected answer bundle: {}", e))?;
+
+        // Offer с паролем: answer обязан нести ответное сообщение CPace.
+        // Состояние CPace забирается только вместе с ответным сообщением: иначе
+        // answer без PAKE со второй попытки прошёл бы как answer на offer без пароля
+        let expects_pake = session.pake_pending.lock().unwrap().is_some();
+        match (expects_pake, bundle.pake.as_deref()) {
+            (true, Some(message)) => {
+                let pending = session.pake_pending.lock().unwrap().take();
+                let Some(key) = pending
+                    .zip(parse_message(message))
+                    .and_then(|(pake, m)| pake.finish(&m))
+                else {
+                    // Состояние CPace израсходовано, второй попытки у этого offer нет
+                    warn!("Rejected answer: invalid PAKE message, closing session");
+                    session.close().await;
+                    return Err(SscError::Pake("invalid PAKE message".into()));
+                };
+                *session.pake_key.lock().unwrap() = Some(key);
+                info!("Answer passphrase exchange (CPace) completed");
+            }
+            (true, None) => {
+                warn!("Rejected answer: passphrase-protected offer answered without PAKE");
+                return Err(SscError::Pake("answer is missing PAKE message".into()));
+            }
+            (false, Some(_)) => {
+                warn!("Rejected answer: unexpected PAKE message");
+                return Err(SscError::Pake("unexpected PAKE message".into()));
+            }
+            (false, None) => {}
+        }
+
+        // Offer одноразовый: повторный answer на него уже не примется
+        *session.pending_offer_id.lock().unwrap() = None;
+        *session.transport_code.lock().unwrap() = None;
+        remember_signer(&session, signer);
+
+        let Some(pc) = ({ session.peer.lock().unwrap().as_ref().cloned() }) else {
+            warn!("No peer connection available");
+            return Err(SscError::NoPeer);
+        };
+
+        // Устанавливаем remote description
+        if let Err(e) = pc.set_remote_description(bundle.sdp_payload.sdp).await {
+            warn!("Failed to set remote description: {:?}", e);
+            return Err(SscError::InvalidSdp(e.to_string()));
+        }
+        info!("Remote description set successfully");
+
+        // Применяем все кандидаты из answer
+        apply_candidates(&pc, bundle.ice_candidates).await;
+        Ok(())
+    }
+
+    /// Offer без ожидания кандидатов (trickle ICE, устаревший API)
+    pub async fn create_offer_trickle(&self) -> Result<SignalingBundle, SscError> {
+        debug!("create_offer_trickle called - creating new peer connection");
+        let session = self.sessions.open(random_id())?;
+        let result = offer_trickle(&session).await;
+        bundle_or_close(session, result).await
+    }
+
+    /// Принятие offer без кандидатов, answer возвращается сразу (trickle ICE)
+    pub async fn accept_offer_trickle(&self, encoded: &str) -> Result<SignalingBundle, SscError> {
+        debug!("accept_offer_trickle called - starting offer processing");
+        let (offer, signer) =
+            open_signed_bundle::<SdpPayload>(encoded, None, &self.sessions.events, None)
+                .and_then(|(offer, signer)| {
+                    check_freshness(offer.ts)?;
+                    mark_offer_used(&offer.id)?;
+                    Ok((offer, signer))
+                })
+                .inspect_err(|e| warn!("Rejected offer: {}", e))?;
+
+        // Кандидаты собеседника могут прийти раньше answer, поэтому сессия регистрируется сразу
+        let offer_id = offer.id.clone();
+        let result = match self.sessions.open(offer.id.clone()) {
+            Ok(session) => {
+                remember_signer(&session, signer);
+                let result = answer_trickle(&session, offer).await;
+                bundle_or_close(session, result).await
+            }
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            unmark_offer_used(&offer_id);
+        }
+        result
+    }
+
+    /// Применение answer без кандидатов (trickle ICE)
+    pub async fn apply_answer_trickle(
+        &self,
+        session_id: &str,
+        encoded: &str,
+    ) -> Result<(), SscError> {
+        debug!("apply_answer_trickle called - starting handshake completion");
+        let session = self.sessions.get(session_id)?;
+        let (answer, signer) =
+            open_signed_bundle::<SdpPayload>(encoded, None, &session.events, Some(session_id))
+                .and_then(|(answer, signer)| {
+                    check_freshness(answer.ts)?;
+                    check_pending_offer(&session, &answer.id)?;
+                    Ok((answer, signer))
+                })
+                .inspect_err(|e| warn!("Rejected answer: {}", e))?;
+        *session.pending_offer_id.lock().unwrap() = None;
+        remember_signer(&session, signer);
+
+        let Some(pc) = ({ session.peer.lock().unwrap().as_ref().cloned() }) else {
+            warn!("No peer connection available for apply_answer_trickle");
+            return Err(SscError::NoPeer);
+        };
+
+        info!("Setting remote description...");
+        pc.set_remote_description(answer.sdp).await.map_err(|e| {
+            warn!("Failed to set remote description: {:?}", e);
+            SscError::InvalidSdp(e.to_string())
+        })?;
+        info!("Remote description set successfully");
+
+        // Применяем отложенные кандидаты
+        apply_pending_candidates(&session, &pc).await;
+        Ok(())
+    }
+
+    /// ICE кандидат собеседника для сессии `session_id` (trickle ICE)
+    pub async fn add_ice_candidate(
+        &self,
+        session_id: &str,
+        candidate: IceCandidate,
+    ) -> Result<(), SscError> {
+        let session = self.sessions.get(session_id)?;
+        ice::add_ice_candidate(&session, candidate).await
+    }
+
+    /// Текст по каналу сессии `session_id`
+    ///
+    /// Если лимит сообщений исчерпан, текст не шифруется старыми ключами, а ждёт
+    /// в очереди нового обмена и уходит после него: тогда результат - `Queued`.
+    /// Ошибка `NonceExhausted` - только при переполненной очереди; если собеседник
+    /// не ответит на обмен, сессия закроется с событием `RekeyFailed`.
+    pub async fn send(&self, session_id: &str, text: &str) -> Result<SendStatus, SscError> {
+        debug!("send called with: {}", Plaintext(text));
+        let session = self.sessions.get(session_id)?;
+        if session.data_ch.lock().unwrap().is_none() {
+            warn!("No data channel available for sending");
+            return Err(SscError::NoDataChannel);
+        }
+        // С версии 2 текст упаковывается во внутренний кадр и выравнивается по длине
+        let plaintext = if framing_enabled(&session) {
+            frame(InnerKind::Text, text.as_bytes())
+        } else {
+            text.as_bytes().to_vec()
+        };
+        // Получаем данные из мьютекса и освобождаем его
+        let result = {
+            let mut crypto_guard = session.crypto.lock().unwrap();
+            let Some(ref mut ctx) = *crypto_guard else {
+                warn!("No crypto context available for sending");
+                return Err(SscError::NoSession);
+            };
+            if !ctx.verified && *REQUIRE_VERIFICATION.lock().unwrap() {
+                warn!("Fingerprint not verified yet, refusing to send");
+                return Err(SscError::NotVerified);
+            }
+            // Пока очередь ждёт новых ключей, новые сообщения встают за ней
+            if session.outbox.lock().unwrap().is_empty() {
+                ctx.seal(&plaintext)
+            } else {
+                Err(SealError::Exhausted(ctx.sent))
+            }
+        }; // мьютекс освобождается здесь
+
+        match result {
+            Ok((epoch, seq_num, ciphertext)) => {
+                debug!(
+                    "Encrypted message with epoch {} seq {}, length: {}",
+                    epoch,
+                    seq_num,
+                    ciphertext.len()
+                );
+                let send_result = send_frame(&session, MessageType::ChatText, ciphertext).await;
+                debug!("Send result: {}", send_result);
+                if !send_result {
+                    return Err(SscError::WebRtc("data channel send failed".into()));
+                }
+                Ok(SendStatus::Sent)
+            }
+            Err(SealError::Exhausted(sent)) => {
+                // Сообщение уйдёт новыми ключами, как только завершится обмен
+                let Some(queued) = queue_until_rekey(&session, plaintext) else {
+                    warn!("Message queue is full while renegotiating keys");
+                    return Err(SscError::NonceExhausted { sent });
+                };
+                if queued == 1 {
+                    warn!(
+                        "Message limit reached after {} messages, queueing until keys are renewed",
+                        sent
+                    );
+                    emit_nonce_exhausted(&session);
+                }
+                start_rekey(&session).await;
+                Ok(SendStatus::Queued)
+            }
+            Err(e) => {
+                warn!("Encryption failed");
+                Err(e.into())
+            }
+        }
+    }
+
+    /// SAS сессии, пока ключи не согласованы - `None`
+    pub fn fingerprint(&self, session_id: &str) -> Result<Option<String>, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let result = session
+            .crypto
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.sas.clone());
+        debug!(
+            "fingerprint called, crypto exists: {}, result: {:?}",
+            result.is_some(),
+            result
+        );
+        Ok(result)
+    }
+
+    /// SAS в нужном формате: hex, words, emoji или numeric
+    pub fn fingerprint_as(
+        &self,
+        session_id: &str,
+        format: SasFormat,
+    ) -> Result<Option<String>, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let result = session
+            .crypto
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.sas_as(format));
+        debug!(
+            "fingerprint_as called with {:?}, result: {:?}",
+            format, result
+        );
+        Ok(result)
+    }
+
+    /// Пользователь подтвердил, что SAS совпадает на обоих устройствах
+    pub fn mark_verified(&self, session_id: &str) -> Result<(), SscError> {
+        let session = self.sessions.get(session_id)?;
+        let mut crypto_guard = session.crypto.lock().unwrap();
+        let Some(ref mut ctx) = *crypto_guard else {
+            debug!("mark_verified called without crypto context");
+            return Err(SscError::NoSession);
+        };
+        ctx.verified = true;
+        info!("Fingerprint marked as verified");
+
+        // Следующие сессии с этим ключом идентичности не потребуют повторной сверки
+        if let Some(peer) = session.remote_identity.lock().unwrap().as_ref() {
+            let pinned = pin_verified(peer);
+            info!(
+                "Pinned identity {} as verified: {}",
+                peer.fingerprint(),
+                pinned
+            );
+            *session.identity_state.lock().unwrap() = Some(IdentityState::Verified);
+        }
+        Ok(())
+    }
+
+    /// Пользователь отверг SAS: разрываем соединение и зануляем ключи этой сессии
+    pub async fn reject(&self, session_id: &str) -> Result<(), SscError> {
+        warn!(
+            "Fingerprint rejected by user, tearing down session {}",
+            session_id
+        );
+        self.sessions.get(session_id)?.close().await;
+        Ok(())
+    }
+
+    /// Подтверждён ли SAS в сессии
+    pub fn is_verified(&self, session_id: &str) -> Result<bool, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let verified = session
+            .crypto
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|c| c.verified);
+        Ok(verified)
+    }
+
+    /// Согласованы ли ключи сессии
+    pub fn is_connected(&self, session_id: &str) -> Result<bool, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let connected = session.crypto.lock().unwrap().is_some();
+        Ok(connected)
+    }
+
+    /// Отпечаток ключа идентичности собеседника в сессии
+    pub fn peer_identity(&self, session_id: &str) -> Result<Option<String>, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let fingerprint = session
+            .remote_identity
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|peer| peer.fingerprint());
+        Ok(fingerprint)
+    }
+
+    /// Сверка ключа идентичности собеседника с закреплёнными контактами
+    ///
+    /// `None` - собеседник не подписал ключ или ключи ещё не согласованы.
+    pub fn identity_state(&self, session_id: &str) -> Result<Option<IdentityState>, SscError> {
+        let session = self.sessions.get(session_id)?;
+        let state = *session.identity_state.lock().unwrap();
+        Ok(state)
+    }
+
+    /// Интервал холостых сообщений; применяется сразу ко всем установленным сессиям
+    pub fn set_cover_traffic(&self, interval_ms: u64) -> Result<(), SscError> {
+        settings::set_cover_traffic(interval_ms)?;
+        for session in self.sessions.all() {
+            restart_cover_traffic(&session);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Пакет сигнализации новой сессии; при ошибке сессия закрывается
+async fn bundle_or_close(
+    session: Arc<Session>,
+    result: Result<String, SscError>,
+) -> Result<SignalingBundle, SscError> {
+    match result {
+        Ok(encoded) => Ok(SignalingBundle {
+            session_id: session.id.clone(),
+            encoded,
+        }),
+        Err(e) => {
+            session.close().await;
+            Err(e)
+        }
+    }
+}
+
+/// Offer без кандидатов: они уйдут отдельно через `add_ice_candidate`
+async fn offer_trickle(session: &Arc<Session>) -> Result<String, SscError> {
+    let pc = new_peer(session, true).await?;
+
+    debug!("Creating offer...");
+    let offer = pc.create_offer(None).await?;
+    info!("Setting local description (offer)...");
+    pc.set_local_description(offer).await?;
+
+    // НЕ ждем ICE gathering - отправляем offer сразу
+    info!("Returning offer immediately (trickle ICE)");
+    *session.pending_offer_id.lock().unwrap() = Some(session.id.clone());
+
+    seal_bundle(
+        session,
+        &SdpPayload {
+            sdp: pc
+                .local_description()
+                .await
+                .ok_or_else(|| SscError::WebRtc("local description missing".into()))?,
+            id: session.id.clone(),
+            ts: chrono::Utc::now().timestamp(),
+        },
+        None,
+    )
+}
+
+/// Answer без кандидатов на проверенный offer
+async fn answer_trickle(session: &Arc<Session>, offer: SdpPayload) -> Result<String, SscError> {
+    let pc = new_peer(session, false).await?;
+
+    info!("Setting remote description (offer)...");
+    pc.set_remote_description(offer.sdp).await.map_err(|e| {
+        warn!("Failed to set remote description: {:?}", e);
+        SscError::InvalidSdp(e.to_string())
+    })?;
+
+    // Применяем отложенные кандидаты
+    apply_pending_candidates(session, &pc).await;
+
+    debug!("Creating answer...");
+    let answer = pc.create_answer(None).await?;
+    info!("Setting local description (answer)...");
+    pc.set_local_description(answer).await?;
+
+    // НЕ ждем ICE gathering
+    info!("Returning answer immediately (trickle ICE)");
+
+    seal_bundle(
+        session,
+        &SdpPayload {
+            sdp: pc
+                .local_description()
+                .await
+                .ok_or_else(|| SscError::WebRtc("local description missing".into()))?,
+            id: offer.id,
+            ts: chrono::Utc::now().timestamp(),
+        },
+        None,
+    )
+}
+
+/// Offer для новой сессии: подписанный (и, с кодом переноса, зашифрованный) пакет
+async fn offer_with_candidates(
+    session: &Arc<Session>,
+    passphrase: Option<String>,
+    transport_code: Option<String>,
+) -> Result<String, SscError> {
+    let connection_id = session.id.clone();
+    *session.transport_code.lock().unwrap() = transport_code.clone();
+
+    let pc = new_peer(session, true).await?;
+
+    debug!("Creating offer...");
+    let offer = pc.create_offer(None).await?;
+    pc.set_local_description(offer).await?;
+
+    // Ждем сбора кандидатов с таймаутом
+    debug!("Waiting for ICE candidates...");
+    let candidates = wait_for_candidates(session, 10).await; // 10 секунд максимум
+
+    debug!("Collected {} ICE candidates", candidates.len());
+    analyze_candidates(&candidates);
+
+    let pake = normalize_passphrase(passphrase)
+        .map(|p| Pake::start(PakeRole::Initiator, &p, &connection_id));
+    let pake_message = pake.as_ref().map(|p| hex::encode(p.message()));
+    if pake.is_some() {
+        info!("Offer is protected by a passphrase (CPace)");
+    }
+    *session.pake_pending.lock().unwrap() = pake;
+    *session.pending_offer_id.lock().unwrap() = Some(connection_id.clone());
+
+    let bundle = ConnectionBundle {
+        sdp_payload: SdpPayload {
+            sdp: pc
+                .local_description()
+                .await
+                .ok_or_else(|| SscError::WebRtc("local description missing".into()))?,
+            id: connection_id,
+            ts: chrono::Utc::now().timestamp(),
+        },
+        ice_candidates: candidates,
+        pake: pake_message,
+    };
+
+    // Кодируем всё вместе, с подписью и MAC
+    seal_bundle(session, &bundle, transport_code.as_deref())
+}
+
+/// Answer на проверенный offer; answer шифруется тем же кодом, что и offer
+async fn answer_with_candidates(
+    session: &Arc<Session>,
+    bundle: ConnectionBundle,
+    pake_message: Option<String>,
+    transport_code: Option<String>,
+) -> Result<String, SscError> {
+    let pc = new_peer(session, false).await?;
+
+    // Устанавливаем remote description
+    pc.set_remote_description(bundle.sdp_payload.sdp)
+        .await
+        .map_err(|e| {
+            warn!("Failed to set remote description: {:?}", e);
+            SscError::InvalidSdp(e.to_string())
+        })?;
+
+    // Применяем все кандидаты из offer
+    apply_candidates(&pc, bundle.ice_candidates).await;
+
+    // Создаем answer
+    let answer = pc.create_answer(None).await?;
+    pc.set_local_description(answer).await?;
+
+    // Ждем сбора кандидатов
+    debug!("Waiting for ICE candidates...");
+    let candidates = wait_for_candidates(session, 10).await;
+
+    debug!("Collected {} ICE candidates for answer", candidates.len());
+    analyze_candidates(&candidates);
+
+    let bundle = ConnectionBundle {
+        sdp_payload: SdpPayload {
+            sdp: pc
+                .local_description()
+                .await
+                .ok_or_else(|| SscError::WebRtc("local description missing".into()))?,
+            id: bundle.sdp_payload.id,
+            ts: chrono::Utc::now().timestamp(),
+        },
+        ice_candidates: candidates,
+        pake: pake_message,
+    };
+
+    // Кодируем всё вместе, с подписью и MAC
+    seal_bundle(session, &bundle, transport_code.as_deref())
+}
+
+/// Запоминает, кем подписан offer или answer собеседника
+fn remember_signer(session: &Session, signer: BundleSigner) {
+    *session.remote_signer.lock().unwrap() = Some(signer.key);
+    *session.bundle_identity.lock().unwrap() = signer.identity;
+}
+
+/// Применяет кандидаты из пакета; ошибка одного кандидата не мешает остальным
+async fn apply_candidates(pc: &RTCPeerConnection, candidates: Vec<IceCandidate>) {
+    for candidate in candidates {
+        debug!("Applying remote candidate: {:?}", candidate);
+        let ice_candidate = RTCIceCandidateInit {
+            candidate: candidate.candidate,
+            sdp_mid: candidate.sdp_mid,
+            sdp_mline_index: candidate.sdp_mline_index,
+            username_fragment: None,
+        };
+
+        if let Err(e) = pc.add_ice_candidate(ice_candidate).await {
+            warn!("Failed to add candidate: {:?}", e);
+        }
+    }
+}
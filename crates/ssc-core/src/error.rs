@@ -0,0 +1,132 @@
+use crate::peer::crypto::SealError;
+use serde::Serialize;
+use std::fmt;
+
+/// Ошибка команды, уходит в UI как `{ kind, detail }`
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum SscError {
+    /// Не разобран base64, GZIP, JSON или hex
+    Decode(String),
+    /// Распакованный пакет больше допустимого (защита от zip-bomb)
+    DecompressionLimit { limit: u64 },
+    /// Подпись пакета сигнализации не сходится
+    BadSignature,
+    /// MAC пакета сигнализации не сходится
+    BadMac,
+    /// Пакет подписан сменившимся ключом идентичности (новый отпечаток)
+    IdentityChanged(String),
+    /// Пакет зашифрован, нужен код переноса
+    TransportCodeRequired,
+    /// Неверный код переноса или пакет повреждён
+    BadTransportCode,
+    /// Код переноса указан, но пакет не зашифрован
+    NotEncrypted,
+    /// Ошибка обмена CPace (нет пароля, лишний пароль, битое сообщение)
+    Pake(String),
+    /// Пакет старше допустимого TTL
+    Expired { age_secs: i64, ttl_secs: i64 },
+    /// Метка времени из будущего больше допустимого расхождения часов
+    FromFuture { ahead_secs: i64 },
+    /// Answer относится к другому offer
+    IdMismatch { expected: String, actual: String },
+    /// Нет ожидающего offer для этого answer
+    NoPendingOffer,
+    /// Offer уже был принят
+    Replayed(String),
+    /// SDP из пакета не принят WebRTC
+    InvalidSdp(String),
+    /// Нет сессии с таким id
+    UnknownSession(String),
+    /// Сессия с таким id уже есть
+    SessionExists(String),
+    /// Нет peer connection
+    NoPeer,
+    /// Нет открытого data channel
+    NoDataChannel,
+    /// Нет установленного криптографического контекста
+    NoSession,
+    /// Отправка запрещена до подтверждения SAS
+    NotVerified,
+    /// Достигнут порог сообщений, идёт повторный обмен ключами
+    NonceExhausted { sent: u64 },
+    /// Ошибка шифрования, подписи или вывода ключей
+    Crypto(String),
+    /// Операция не уложилась в отведённое время
+    Timeout(String),
+    /// Ошибка WebRTC
+    WebRtc(String),
+    /// Недопустимый аргумент команды
+    InvalidArgument(String),
+    /// Ошибка чтения или записи файлов приложения
+    Storage(String),
+}
+
+impl fmt::Display for SscError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(e) => write!(f, "malformed payload: {}", e),
+            Self::DecompressionLimit { limit } => {
+                write!(f, "decompressed payload exceeds {} bytes", limit)
+            }
+            Self::BadSignature => write!(f, "bundle signature does not verify"),
+            Self::BadMac => write!(f, "bundle MAC does not verify"),
+            Self::IdentityChanged(fp) => {
+                write!(f, "bundle signed by changed identity key {}", fp)
+            }
+            Self::TransportCodeRequired => {
+                write!(f, "bundle is encrypted, transport code required")
+            }
+            Self::BadTransportCode => write!(f, "wrong transport code or corrupted bundle"),
+            Self::NotEncrypted => write!(f, "transport code given but bundle is not encrypted"),
+            Self::Pake(e) => write!(f, "passphrase exchange failed: {}", e),
+            Self::Expired { age_secs, ttl_secs } => {
+                write!(f, "payload expired: {}s old, ttl {}s", age_secs, ttl_secs)
+            }
+            Self::FromFuture { ahead_secs } => {
+                write!(f, "payload timestamp is {}s in the future", ahead_secs)
+            }
+            Self::IdMismatch { expected, actual } => write!(
+                f,
+                "answer is for connection {}, pending offer is {}",
+                actual, expected
+            ),
+            Self::NoPendingOffer => write!(f, "no pending offer for this answer"),
+            Self::Replayed(id) => write!(f, "offer {} was already accepted", id),
+            Self::InvalidSdp(e) => write!(f, "invalid SDP: {}", e),
+            Self::UnknownSession(id) => write!(f, "no session {}", id),
+            Self::SessionExists(id) => write!(f, "session {} already exists", id),
+            Self::NoPeer => write!(f, "no peer connection"),
+            Self::NoDataChannel => write!(f, "no data channel"),
+            Self::NoSession => write!(f, "no established crypto session"),
+            Self::NotVerified => write!(f, "fingerprint is not verified yet"),
+            Self::NonceExhausted { sent } => write!(
+                f,
+                "message limit reached after {} messages and the queue is full, renegotiating keys",
+                sent
+            ),
+            Self::Crypto(e) => write!(f, "crypto failure: {}", e),
+            Self::Timeout(e) => write!(f, "timed out: {}", e),
+            Self::WebRtc(e) => write!(f, "WebRTC error: {}", e),
+            Self::InvalidArgument(e) => write!(f, "invalid argument: {}", e),
+            Self::Storage(e) => write!(f, "storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SscError {}
+
+impl From<webrtc::Error> for SscError {
+    fn from(e: webrtc::Error) -> Self {
+        Self::WebRtc(e.to_string())
+    }
+}
+
+impl From<SealError> for SscError {
+    fn from(e: SealError) -> Self {
+        match e {
+            SealError::Exhausted(sent) => Self::NonceExhausted { sent },
+            SealError::Encrypt => Self::Crypto("encryption failed".into()),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::logger::Plaintext;
+use crate::peer::identity::IdentityChanged;
+use crate::peer::negotiation::IncompatiblePeer;
+use crate::peer::session::Session;
+use crate::peer::state::EVENT_CHANNEL_CAPACITY;
+use crate::peer::traffic::stop_cover_traffic;
+use serde::Serialize;
+use std::fmt;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Событие сессии для UI и других клиентов библиотеки
+#[derive(Clone)]
+pub enum Event {
+    /// Ключи согласованы, можно сверять SAS
+    Connected {
+        session_id: String,
+    },
+    /// Сессия закончилась, её ключи занулены
+    Disconnected {
+        session_id: String,
+    },
+    /// Расшифрованное сообщение собеседника
+    Message {
+        session_id: String,
+        text: String,
+    },
+    /// У сторон нет общей версии протокола или набора алгоритмов
+    IncompatiblePeer {
+        session_id: String,
+        details: IncompatiblePeer,
+    },
+    /// Закреплённый контакт пришёл с другим ключом идентичности
+    ///
+    /// `session_id` пуст, если пакет сигнализации ещё не удалось отнести к сессии.
+    IdentityChanged {
+        session_id: Option<String>,
+        details: IdentityChanged,
+    },
+    /// Собеседник вывел другие ключи
+    KeyConfirmationFailed {
+        session_id: String,
+    },
+    /// Исчерпан лимит сообщений на один обмен ключами, идёт новый обмен;
+    /// сообщения ждут в очереди и уйдут после него
+    NonceExhausted {
+        session_id: String,
+    },
+    /// Собеседник не ответил на повторный обмен ключами, сессия закрыта
+    RekeyFailed {
+        session_id: String,
+    },
+    ConnectionProblem {
+        session_id: String,
+    },
+    ConnectionRecovering {
+        session_id: String,
+    },
+    ConnectionRecovered {
+        session_id: String,
+    },
+    ConnectionFailed {
+        session_id: String,
+    },
+}
+
+/// Payload события: id сессии и данные самого события
+#[derive(Serialize)]
+struct SessionEvent<'a, T> {
+    session_id: Option<&'a str>,
+    #[serde(flatten)]
+    payload: T,
+}
+
+/// Событие без данных, кроме id сессии
+#[derive(Serialize)]
+struct NoPayload {}
+
+/// Payload `ssc-message`
+#[derive(Serialize)]
+struct MessagePayload<'a> {
+    text: &'a str,
+}
+
+fn tagged<T: Serialize>(session_id: Option<&str>, payload: T) -> serde_json::Value {
+    serde_json::to_value(SessionEvent {
+        session_id,
+        payload,
+    })
+    .unwrap_or_default()
+}
+
+impl Event {
+    /// Имя события в UI
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Connected { .. } => "ssc-connected",
+            Self::Disconnected { .. } => "ssc-disconnected",
+            Self::Message { .. } => "ssc-message",
+            Self::IncompatiblePeer { .. } => "ssc-incompatible-peer",
+            Self::IdentityChanged { .. } => "ssc-identity-changed",
+            Self::KeyConfirmationFailed { .. } => "ssc-key-confirmation-failed",
+            Self::NonceExhausted { .. } => "ssc-nonce-exhausted",
+            Self::RekeyFailed { .. } => "ssc-rekey-failed",
+            Self::ConnectionProblem { .. } => "ssc-connection-problem",
+            Self::ConnectionRecovering { .. } => "ssc-connection-recovering",
+            Self::ConnectionRecovered { .. } => "ssc-connection-recovered",
+            Self::ConnectionFailed { .. } => "ssc-connection-failed",
+        }
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Self::IdentityChanged { session_id, .. } => session_id.as_deref(),
+            Self::Connected { session_id }
+            | Self::Disconnected { session_id }
+            | Self::Message { session_id, .. }
+            | Self::IncompatiblePeer { session_id, .. }
+            | Self::KeyConfirmationFailed { session_id }
+            | Self::NonceExhausted { session_id }
+            | Self::RekeyFailed { session_id }
+            | Self::ConnectionProblem { session_id }
+            | Self::ConnectionRecovering { session_id }
+            | Self::ConnectionRecovered { session_id }
+            | Self::ConnectionFailed { session_id } => Some(session_id),
+        }
+    }
+
+    /// Payload события в UI: `session_id` и поля события
+    pub fn payload(&self) -> serde_json::Value {
+        match self {
+            Self::Message { session_id, text } => tagged(Some(session_id), MessagePayload { text }),
+            Self::IncompatiblePeer {
+                session_id,
+                details,
+            } => tagged(Some(session_id), details),
+            Self::IdentityChanged {
+                session_id,
+                details,
+            } => tagged(session_id.as_deref(), details),
+            _ => tagged(self.session_id(), NoPayload {}),
+        }
+    }
+}
+
+/// Текст сообщения скрыт так же, как в логах
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message { session_id, text } => f
+                .debug_struct("Message")
+                .field("session_id", session_id)
+                .field("text", &Plaintext(text).to_string())
+                .finish(),
+            _ => write!(f, "{} {:?}", self.name(), self.session_id()),
+        }
+    }
+}
+
+/// Рассылка событий всех сессий одного клиента
+#[derive(Clone)]
+pub struct Events(broadcast::Sender<Event>);
+
+impl Events {
+    pub fn new() -> Self {
+        Self(broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.0.subscribe()
+    }
+
+    pub fn emit(&self, event: Event) {
+        debug!("emit {} for session {:?}", event.name(), event.session_id());
+        // Без подписчиков событие просто теряется
+        if self.0.send(event).is_err() {
+            debug!("No event subscribers");
+        }
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn emit_connected(session: &Session) {
+    debug!("emit_connected called - setting was_connected flag");
+    *session.was_connected.lock().unwrap() = true;
+    session.events.emit(Event::Connected {
+        session_id: session.id.clone(),
+    });
+}
+
+/// Сессия закончилась: зануляем её ключи, убираем из `SessionManager` и сообщаем подписчикам
+pub fn emit_disconnected(session: &Session) {
+    debug!("emit_disconnected called - clearing session {}", session.id);
+    stop_cover_traffic(session);
+    session.clear_keys();
+    *session.local_hello.lock().unwrap() = None;
+    *session.pake_key.lock().unwrap() = None;
+    session.clear_candidates();
+
+    if let Some(manager) = session.manager.upgrade() {
+        manager.remove(session);
+    }
+    session.events.emit(Event::Disconnected {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_message(session: &Session, text: &str) {
+    session.events.emit(Event::Message {
+        session_id: session.id.clone(),
+        text: text.to_string(),
+    });
+}
+
+pub fn emit_incompatible_peer(session: &Session, details: &IncompatiblePeer) {
+    debug!(
+        "emit_incompatible_peer called - no common protocol: {:?}",
+        details
+    );
+    session.events.emit(Event::IncompatiblePeer {
+        session_id: session.id.clone(),
+        details: details.clone(),
+    });
+}
+
+pub fn emit_identity_changed(events: &Events, session_id: Option<&str>, details: &IdentityChanged) {
+    debug!(
+        "emit_identity_changed called - contact {} changed identity key: {} -> {}",
+        details.installation_id, details.previous_fingerprint, details.new_fingerprint
+    );
+    events.emit(Event::IdentityChanged {
+        session_id: session_id.map(str::to_string),
+        details: details.clone(),
+    });
+}
+
+pub fn emit_key_confirmation_failed(session: &Session) {
+    warn!("emit_key_confirmation_failed called - peer derived different keys");
+    session.events.emit(Event::KeyConfirmationFailed {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_nonce_exhausted(session: &Session) {
+    debug!("emit_nonce_exhausted called - message limit reached, renegotiating keys");
+    session.events.emit(Event::NonceExhausted {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_rekey_failed(session: &Session) {
+    warn!("emit_rekey_failed called - peer did not answer key renegotiation");
+    session.events.emit(Event::RekeyFailed {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_connection_problem(session: &Session) {
+    debug!("emit_connection_problem called - connection issues detected");
+    session.events.emit(Event::ConnectionProblem {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_connection_recovering(session: &Session) {
+    debug!("emit_connection_recovering called - connection is recovering");
+    session.events.emit(Event::ConnectionRecovering {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_connection_recovered(session: &Session) {
+    debug!("emit_connection_recovered called - connection recovered");
+    session.events.emit(Event::ConnectionRecovered {
+        session_id: session.id.clone(),
+    });
+}
+
+pub fn emit_connection_failed(session: &Session) {
+    warn!("emit_connection_failed called - connection recovery failed");
+    session.events.emit(Event::ConnectionFailed {
+        session_id: session.id.clone(),
+    });
+}
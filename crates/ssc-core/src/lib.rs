@@ -0,0 +1,15 @@
+//! Протокол ssc без привязки к UI: сигнализация, обмен ключами и зашифрованные сессии поверх WebRTC
+//!
+//! Точка входа - [`Client`]; события сессий приходят через [`Client::subscribe`].
+
+pub mod client;
+pub mod error;
+pub mod events;
+pub mod logger;
+pub mod peer;
+pub mod settings;
+pub mod utils;
+
+pub use client::Client;
+pub use error::SscError;
+pub use events::Event;
@@ -0,0 +1,79 @@
+use crate::peer::state::UNSAFE_DIAGNOSTICS;
+use std::fmt;
+use tracing::debug;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
+use webrtc::peer_connection::RTCPeerConnection;
+
+/// Включён ли вывод чувствительных данных в лог (только до перезапуска)
+pub fn unsafe_diagnostics() -> bool {
+    *UNSAFE_DIAGNOSTICS.lock().unwrap()
+}
+
+/// Текст сообщения чата: в логе только пометка, сам текст - лишь в режиме unsafe diagnostics
+pub struct Plaintext<'a>(pub &'a str);
+
+/// Ключевой материал (в том числе публичные ключи обмена): печатается в hex только в unsafe diagnostics
+pub struct KeyMaterial<'a>(pub &'a [u8]);
+
+/// Пароли, коды и учётные данные TURN
+pub struct Secret<'a>(pub &'a str);
+
+impl fmt::Display for Plaintext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if unsafe_diagnostics() {
+            write!(f, "{:?}", self.0)
+        } else {
+            f.write_str("<redacted text>")
+        }
+    }
+}
+
+impl fmt::Display for KeyMaterial<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if unsafe_diagnostics() {
+            f.write_str(&hex::encode(self.0))
+        } else {
+            f.write_str("<redacted key>")
+        }
+    }
+}
+
+impl fmt::Display for Secret<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if unsafe_diagnostics() {
+            write!(f, "{:?}", self.0)
+        } else if self.0.is_empty() {
+            f.write_str("<empty>")
+        } else {
+            f.write_str("<redacted secret>")
+        }
+    }
+}
+
+/// Печать ICE-candidate при появлении (Trickle-ICE)
+pub async fn dump_candidate(label: &str, cand: &RTCIceCandidate) {
+    if let Ok(init) = cand.to_json() {
+        debug!("Trickle {label}: candidate={} sdp_mid={:?} sdp_mline_index={:?} username_fragment={:?}",
+            init.candidate, init.sdp_mid, init.sdp_mline_index, init.username_fragment);
+    }
+}
+
+/// Быстрый снимок getStats → выбранная пара
+pub async fn dump_selected_pair(pc: &RTCPeerConnection, moment: &str) {
+    let stats = pc.get_stats().await;
+    for (_, v) in stats.reports {
+        if let webrtc::stats::StatsReportType::CandidatePair(pair) = v {
+            if pair.nominated {
+                debug!(
+                    "STATS {moment}: {}:{}  type: {:?}  bytes={}/{} state={:?}",
+                    pair.local_candidate_id,
+                    pair.remote_candidate_id,
+                    pair.stats_type,
+                    pair.bytes_sent,
+                    pair.bytes_received,
+                    pair.state
+                );
+            }
+        }
+    }
+}
@@ -1,19 +1,18 @@
-use crate::logger::{
-    dump_candidate, dump_selected_pair, emit_connected, emit_connection_failed,
-    emit_connection_problem, emit_connection_recovered, emit_connection_recovering,
-    emit_disconnected, log,
+use crate::error::SscError;
+use crate::events::{
+    emit_connected, emit_connection_failed, emit_connection_problem, emit_connection_recovered,
+    emit_connection_recovering, emit_disconnected,
 };
+use crate::logger::{dump_candidate, dump_selected_pair};
 use crate::peer::data_channel::attach_dc;
-use crate::peer::state::{
-    COLLECTING_CANDIDATES, CRYPTO, DISCONNECT_TASK, GRACE_PERIOD, LOCAL_CANDIDATES,
-    USER_ICE_SERVERS,
-};
+use crate::peer::session::Session;
+use crate::peer::state::{GRACE_PERIOD, USER_ICE_SERVERS};
 use crate::peer::types::IceCandidate;
 use crate::peer::types::ServerConfig;
 use crate::utils::add_ice_url_scheme;
 use std::sync::Arc;
-use tauri::command;
 use tokio::time::sleep;
+use tracing::{debug, info, warn};
 use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
 use webrtc::peer_connection::policy::bundle_policy::RTCBundlePolicy;
 use webrtc::peer_connection::policy::rtcp_mux_policy::RTCRtcpMuxPolicy;
@@ -27,25 +26,33 @@ use webrtc::{
     },
 };
 
-/// создаём Peer; если `initiator`, то сами делаем data-channel
-pub async fn new_peer(initiator: bool, connection_id: String) -> Arc<RTCPeerConnection> {
+/// создаём Peer сессии; если `initiator`, то сами делаем data-channel
+///
+/// Обработчики держат слабую ссылку на сессию: peer connection хранится в ней же.
+pub async fn new_peer(
+    session: &Arc<Session>,
+    initiator: bool,
+) -> Result<Arc<RTCPeerConnection>, SscError> {
     let api = APIBuilder::new().build();
 
     // Получаем пользовательские серверы если они установлены
     let custom_servers = USER_ICE_SERVERS.lock().unwrap().clone();
     let config = rtc_config(custom_servers);
 
-    let pc = Arc::new(api.new_peer_connection(config).await.unwrap());
+    let pc = Arc::new(api.new_peer_connection(config).await?);
 
     // Начинаем сбор кандидатов
-    *COLLECTING_CANDIDATES.lock().unwrap() = true;
-    LOCAL_CANDIDATES.lock().unwrap().clear();
+    *session.collecting_candidates.lock().unwrap() = true;
+    session.local_candidates.lock().unwrap().clear();
 
     // Обработчик для сбора локальных кандидатов
+    let weak = Arc::downgrade(session);
     pc.on_ice_candidate(Box::new(move |cand: Option<RTCIceCandidate>| {
-        let conn_id = connection_id.clone();
+        let Some(session) = weak.upgrade() else {
+            return Box::pin(async {});
+        };
         if let Some(c) = cand {
-            tauri::async_runtime::spawn({
+            tokio::spawn({
                 let c = c.clone();
                 async move {
                     dump_candidate("LOCAL", &c).await;
@@ -56,115 +63,124 @@ pub async fn new_peer(initiator: bool, connection_id: String) -> Arc<RTCPeerConn
                             candidate: init.candidate,
                             sdp_mid: init.sdp_mid,
                             sdp_mline_index: init.sdp_mline_index,
-                            connection_id: conn_id,
+                            connection_id: session.id.clone(),
                         };
 
                         // Всегда сохраняем кандидат, независимо от флага collecting
-                        LOCAL_CANDIDATES.lock().unwrap().push(ice_candidate);
-                        log(&format!(
-                            "Added ICE candidate, total count: {}",
-                            LOCAL_CANDIDATES.lock().unwrap().len()
-                        ));
+                        let mut candidates = session.local_candidates.lock().unwrap();
+                        candidates.push(ice_candidate);
+                        debug!("Added ICE candidate, total count: {}", candidates.len());
                     }
                 }
             });
         } else {
             // cand == None означает конец сбора
-            log("ICE candidate gathering completed (null candidate received)");
-            *COLLECTING_CANDIDATES.lock().unwrap() = false;
+            debug!("ICE candidate gathering completed (null candidate received)");
+            *session.collecting_candidates.lock().unwrap() = false;
         }
         Box::pin(async {})
     }));
 
     // Добавляем обработчик ICE gathering state для отладки
     pc.on_ice_gathering_state_change(Box::new(move |state| {
-        log(&format!("ICE gathering state changed to: {:?}", state));
+        debug!("ICE gathering state changed to: {:?}", state);
         Box::pin(async {})
     }));
 
-    // делаем копию для обработчика состояний
-    let pc_state = pc.clone();
+    // слабые ссылки для обработчика состояний: и pc, и обработчик живут в сессии
+    let pc_state = Arc::downgrade(&pc);
+    let weak = Arc::downgrade(session);
 
     pc.on_peer_connection_state_change(Box::new(move |st: RTCPeerConnectionState| {
-        log(&format!("Peer connection state changed to: {:?}", st));
+        let (Some(session), Some(pc_state)) = (weak.upgrade(), pc_state.upgrade()) else {
+            return Box::pin(async {});
+        };
+        info!(
+            "Peer connection state of session {} changed to: {:?}",
+            session.id, st
+        );
 
         match st {
             RTCPeerConnectionState::Connected => {
-                log("Peer connection connected - canceling any pending disconnect task");
+                info!("Peer connection connected - canceling any pending disconnect task");
                 // отменяем отложенный disconnect, если он был
-                if let Some(handle) = DISCONNECT_TASK.lock().unwrap().take() {
-                    log("Aborting pending disconnect task");
+                if let Some(handle) = session.disconnect_task.lock().unwrap().take() {
+                    info!("Aborting pending disconnect task");
                     handle.abort();
                 }
 
                 // повторно дёргаем UI, если контекст уже готов
-                let crypto_exists = CRYPTO.lock().unwrap().is_some();
+                let crypto_exists = session.crypto.lock().unwrap().is_some();
                 if crypto_exists {
-                    log("Crypto context exists - re-emitting connected event");
-                    emit_connection_recovered();
-                    emit_connected();
+                    info!("Crypto context exists - re-emitting connected event");
+                    emit_connection_recovered(&session);
+                    emit_connected(&session);
                 } else {
-                    log("Peer connection connected - waiting for crypto context");
+                    info!("Peer connection connected - waiting for crypto context");
                 }
             }
 
             RTCPeerConnectionState::Disconnected | RTCPeerConnectionState::Failed => {
-                log(&format!("Peer connection {:?} - starting grace period", st));
+                info!("Peer connection {:?} - starting grace period", st);
 
                 // уже ожидаем? – ничего не делаем
-                if DISCONNECT_TASK.lock().unwrap().is_some() {
-                    log("Disconnect task already pending, ignoring");
+                if session.disconnect_task.lock().unwrap().is_some() {
+                    info!("Disconnect task already pending, ignoring");
                     return Box::pin(async {});
                 }
 
                 let pc_stats = pc_state.clone();
-                tauri::async_runtime::spawn(async move {
+                tokio::spawn(async move {
                     dump_selected_pair(&pc_stats, "BEFORE-FAIL").await;
                 });
 
                 // Уведомляем о проблемах с подключением
-                emit_connection_problem();
+                emit_connection_problem(&session);
 
                 // ставим отложенную проверку
-                let handle = tauri::async_runtime::spawn({
+                let handle = tokio::spawn({
                     let pc = pc_state.clone(); // используем копию, а не исходный pc
+                    let session = session.clone();
                     async move {
-                        log(&format!(
-                            "Grace period started, waiting {} s",
-                            GRACE_PERIOD.as_secs()
-                        ));
-                        emit_connection_recovering();
+                        info!("Grace period started, waiting {} s", GRACE_PERIOD.as_secs());
+                        emit_connection_recovering(&session);
                         sleep(GRACE_PERIOD).await;
 
                         let state_now = pc.connection_state();
-                        let crypto_exists = CRYPTO.lock().unwrap().is_some();
-                        log(&format!(
+                        let crypto_exists = session.crypto.lock().unwrap().is_some();
+                        info!(
                             "Grace over ➜ state={:?}, crypto_exists={}",
                             state_now, crypto_exists
-                        ));
+                        );
+
+                        // Задача завершает себя сама: хэндл убирается без abort,
+                        // иначе state() навсегда останется Recovering
+                        session.disconnect_task.lock().unwrap().take();
 
-                        // если соединение так и не восстановилось — отправляем событие неудачного восстановления
+                        // если соединение так и не восстановилось — сообщаем об этом
+                        // и закрываем сессию, убирая её из SessionManager
                         if state_now != RTCPeerConnectionState::Connected {
-                            emit_connection_failed();
+                            emit_connection_failed(&session);
+                            session.close().await;
                         } else {
-                            log("Connection recovered during grace period");
+                            info!("Connection recovered during grace period");
                         }
                     }
                 });
-                *DISCONNECT_TASK.lock().unwrap() = Some(handle);
+                *session.disconnect_task.lock().unwrap() = Some(handle);
             }
 
             RTCPeerConnectionState::Closed => {
-                log("Peer connection closed - emitting disconnected immediately");
+                info!("Peer connection closed - emitting disconnected immediately");
                 // отменяем отложенный disconnect, если он был
-                if let Some(handle) = DISCONNECT_TASK.lock().unwrap().take() {
+                if let Some(handle) = session.disconnect_task.lock().unwrap().take() {
                     handle.abort();
                 }
-                emit_disconnected();
+                emit_disconnected(&session);
             }
 
             _ => {
-                log(&format!("Peer connection state: {:?} - ignoring", st));
+                info!("Peer connection state: {:?} - ignoring", st);
             }
         }
         Box::pin(async {})
@@ -173,16 +189,19 @@ pub async fn new_peer(initiator: bool, connection_id: String) -> Arc<RTCPeerConn
     if initiator {
         let dc = pc
             .create_data_channel("ssc-data", Some(RTCDataChannelInit::default()))
-            .await
-            .unwrap();
-        attach_dc(&dc);
+            .await?;
+        attach_dc(session, &dc);
     } else {
-        pc.on_data_channel(Box::new(|dc: Arc<RTCDataChannel>| {
-            attach_dc(&dc);
+        let weak = Arc::downgrade(session);
+        pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
+            if let Some(session) = weak.upgrade() {
+                attach_dc(&session, &dc);
+            }
             Box::pin(async {})
         }));
     }
-    pc
+    *session.peer.lock().unwrap() = Some(pc.clone());
+    Ok(pc)
 }
 
 /// Создает конфигурацию для peer connection
@@ -228,33 +247,35 @@ pub fn get_user_ice_servers(servers: Vec<ServerConfig>) -> Vec<RTCIceServer> {
         .collect()
 }
 
-/// Устанавливает пользовательские ICE серверы, возвращает true при успехе, false при ошибке
-#[command]
-pub fn set_ice_servers(servers: Vec<ServerConfig>) -> bool {
-    log(&format!("Setting {} custom ICE servers", servers.len()));
+/// Устанавливает пользовательские ICE серверы
+pub fn set_ice_servers(servers: Vec<ServerConfig>) -> Result<(), SscError> {
+    info!("Setting {} custom ICE servers", servers.len());
 
     // Валидация серверов
     for server in &servers {
         if server.url.is_empty() {
-            log("Server URL cannot be empty");
-            return false;
+            warn!("Server URL cannot be empty");
+            return Err(SscError::InvalidArgument(
+                "server URL cannot be empty".into(),
+            ));
         }
 
         if server.r#type == "turn" && (server.username.is_none() || server.credential.is_none()) {
-            log("TURN servers require username and credential");
-            return false;
+            warn!("TURN servers require username and credential");
+            return Err(SscError::InvalidArgument(
+                "TURN servers require username and credential".into(),
+            ));
         }
     }
 
     *USER_ICE_SERVERS.lock().unwrap() = Some(servers);
-    log("Custom ICE servers set successfully");
-    true
+    info!("Custom ICE servers set successfully");
+    Ok(())
 }
 
 /// Получает пользовательские ICE серверы, возвращает дефолтные серверы если не установлены
-#[command]
-pub fn get_ice_servers() -> Vec<ServerConfig> {
-    USER_ICE_SERVERS.lock().unwrap().clone().unwrap_or_else(|| {
+pub fn get_ice_servers() -> Result<Vec<ServerConfig>, SscError> {
+    Ok(USER_ICE_SERVERS.lock().unwrap().clone().unwrap_or_else(|| {
         // Возвращаем дефолтные серверы в формате ServerConfig
         vec![
             ServerConfig {
@@ -272,5 +293,5 @@ pub fn get_ice_servers() -> Vec<ServerConfig> {
                 credential: None,
             },
         ]
-    })
+    }))
 }
@@ -0,0 +1,451 @@
+use crate::error::SscError;
+use crate::peer::double_ratchet::DoubleRatchet;
+use crate::peer::negotiation::CipherSuite;
+use crate::peer::ratchet::{RecvChain, SendChain};
+use crate::peer::sas::{self, SasFormat, SAS_LEN};
+use crate::peer::session::Session;
+use crate::peer::state::{EPOCH_LEN, NONCE_LIMIT, REPLAY_WINDOW_SIZE, SEQ_LEN, TAG_LEN};
+use chacha20poly1305::{aead::Nonce, ChaCha20Poly1305};
+use hkdf::Hkdf;
+use ring::{agreement, hmac, rand as ring_rand};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Безопасная обёртка для ключа с автоматической очисткой памяти
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ZeroizedKey {
+    pub key: [u8; 32],
+}
+
+impl ZeroizedKey {
+    pub(crate) fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+}
+
+/// Скользящее окно защиты от replay (как в IPsec/DTLS)
+///
+/// `top` - наибольший принятый sequence number, бит `i` в `bitmap`
+/// означает, что номер `top - i` уже был принят.
+#[derive(Zeroize, Default)]
+pub struct ReplayWindow {
+    top: u64,
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    /// Проверяет, можно ли принять номер, не изменяя состояние окна
+    pub fn check(&self, seq: u64) -> bool {
+        // Номера начинаются с 1
+        if seq == 0 {
+            return false;
+        }
+        if seq > self.top {
+            return true;
+        }
+        let offset = self.top - seq;
+        if offset >= REPLAY_WINDOW_SIZE {
+            // Слишком старый номер - вышел за пределы окна
+            return false;
+        }
+        self.bitmap & (1 << offset) == 0
+    }
+
+    /// Отмечает номер как принятый; вызывать только после успешной аутентификации
+    pub fn accept(&mut self, seq: u64) {
+        if seq > self.top {
+            let shift = seq - self.top;
+            self.bitmap = if shift >= REPLAY_WINDOW_SIZE {
+                0
+            } else {
+                self.bitmap << shift
+            };
+            self.bitmap |= 1;
+            self.top = seq;
+        } else {
+            self.bitmap |= 1 << (self.top - seq);
+        }
+    }
+}
+
+/// Причина отказа в расшифровке входящего сообщения
+#[derive(Debug)]
+pub enum OpenError {
+    /// Сообщение короче заголовка и тега аутентификации
+    TooShort(usize),
+    /// Номер уже был принят или вышел за пределы окна
+    Replay(u64),
+    /// Ошибка аутентификации AEAD
+    Decrypt(u64),
+    /// Эпоха слишком старая или слишком далеко впереди
+    UnknownEpoch(u32),
+    /// Пропущено больше сообщений, чем разрешено хранить ключей
+    SkipLimit(u64),
+}
+
+/// Причина отказа в шифровании исходящего сообщения
+#[derive(Debug)]
+pub enum SealError {
+    /// Достигнут порог сообщений для текущего обмена ключами
+    Exhausted(u64),
+    /// Ошибка AEAD или переполнение счётчика цепочки
+    Encrypt,
+}
+
+/// Состояние храповика сессии
+pub enum RatchetState {
+    /// HKDF-цепочка: ключи сдвигаются каждые N сообщений или M минут
+    Symmetric { send: SendChain, recv: RecvChain },
+    /// Double Ratchet: HKDF-цепочки плюс периодический DH для восстановления после компрометации
+    Double(DoubleRatchet),
+}
+
+/// Контекст шифрования
+pub struct CryptoCtx {
+    pub ratchet: RatchetState, // Ключи зануляются при сдвиге храповика
+    pub sas: String,
+    sas_bytes: [u8; SAS_LEN], // те же 48 бит, из которых строится `sas`
+    pub verified: bool,       // пользователь подтвердил совпадение SAS
+    pub pake: bool,           // ключи привязаны к общему паролю через CPace
+    pub sent: u64,            // Сообщений отправлено с момента обмена ключами
+    // Секрет для привязки следующего обмена ключами к текущему
+    pub rekey_secret: ZeroizedKey,
+    // Ключ подтверждения: каждая сторона доказывает, что вывела те же ключи
+    confirm_key: ZeroizedKey,
+    transcript: [u8; 32],
+    is_low: bool, // наш публичный ключ меньше ключа собеседника
+}
+
+impl Drop for CryptoCtx {
+    fn drop(&mut self) {
+        // Зануляем все чувствительные данные
+        self.sas.zeroize();
+        self.sas_bytes.zeroize();
+        self.sent.zeroize();
+        self.transcript.zeroize();
+        // Ключи цепочек автоматически очистятся благодаря ZeroizeOnDrop
+    }
+}
+
+/// Генерируем новую пару X25519 для обмена ключами сессии, возвращаем публичный ключ
+pub fn generate_keypair(session: &Session) -> [u8; 32] {
+    let rng = ring_rand::SystemRandom::new();
+    let my_priv = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+    let my_pub = my_priv.compute_public_key().unwrap();
+    let my_pub_bytes = <[u8; 32]>::try_from(my_pub.as_ref()).unwrap();
+    *session.my_priv.lock().unwrap() = Some(my_priv);
+    *session.my_pub.lock().unwrap() = Some(my_pub_bytes);
+    my_pub_bytes
+}
+
+/// Строки `a=fingerprint:` из SDP - отпечатки DTLS-сертификата
+pub fn dtls_fingerprints(sdp: &str) -> String {
+    sdp.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("a=fingerprint:"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Данные одной стороны, входящие в транскрипт обмена ключами
+pub struct TranscriptSide<'a> {
+    pub public: &'a [u8; 32],
+    pub fingerprint: &'a str, // строки `a=fingerprint:` из SDP этой стороны
+    pub hello: &'a [u8],      // Hello этой стороны в том виде, как он ушёл в канал
+    pub signer: &'a [u8; 32], // ключ подписи offer или answer этой стороны
+}
+
+/// Хэш транскрипта обмена ключами
+///
+/// Покрывает оба X25519-ключа, оба DTLS fingerprint, оба Hello и ключи подписи
+/// обоих пакетов сигнализации в каноническом порядке (сначала сторона с меньшим
+/// публичным ключом), поэтому подмена любого из них, включая откат версии
+/// или набора алгоритмов и переподпись пакета, меняет ключи и SAS.
+pub fn transcript_hash(local: &TranscriptSide, remote: &TranscriptSide) -> [u8; 32] {
+    let (first, second) = if local.public < remote.public {
+        (local, remote)
+    } else {
+        (remote, local)
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"ssc-kex-v1");
+    for side in [first, second] {
+        hasher.update(side.public);
+        hasher.update((side.fingerprint.len() as u32).to_be_bytes());
+        hasher.update(side.fingerprint.as_bytes());
+        hasher.update((side.hello.len() as u32).to_be_bytes());
+        hasher.update(side.hello);
+        hasher.update(side.signer);
+    }
+    hasher.finalize().into()
+}
+
+/// Создаём контекст шифрования
+///
+/// `transcript` - хэш из `transcript_hash`, он входит в HKDF info.
+/// При повторном обмене ключами `previous` - текущий контекст: его секрет
+/// подмешивается в HKDF, поэтому новые ключи наследуют проверенный SAS.
+/// `pake_key` - результат CPace по паролю из offer, добавляется к общему секрету.
+/// `kem_secret` - общие секреты ML-KEM в гибридном режиме, идут в HKDF сразу после X25519.
+pub fn build_ctx(
+    session: &Session,
+    peer_pub: &[u8; 32],
+    transcript: &[u8; 32],
+    suite: CipherSuite,
+    pake_key: Option<&ZeroizedKey>,
+    kem_secret: Option<&[u8]>,
+    previous: Option<&CryptoCtx>,
+) -> Result<CryptoCtx, SscError> {
+    // ----- свой ключ -----
+    let my_pub = session
+        .my_pub
+        .lock()
+        .unwrap()
+        .ok_or_else(|| SscError::Crypto("public key missing during key exchange".into()))?;
+    let my_priv = session
+        .my_priv
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| SscError::Crypto("private key missing during key exchange".into()))?;
+
+    // ----- общий секрет -----
+    // Ключ собеседника приходит из сети: вырожденная точка даёт ошибку, а не панику
+    let peer_pub_key = agreement::UnparsedPublicKey::new(&agreement::X25519, peer_pub);
+    let mut shared = agreement::agree_ephemeral(my_priv, &peer_pub_key, |secret| secret.to_vec())
+        .map_err(|_| SscError::Crypto("X25519 agreement failed".into()))?;
+    // Гибридный режим: ключи устоят, пока не взломан хотя бы один из двух обменов
+    if let Some(kem_secret) = kem_secret {
+        shared.extend_from_slice(kem_secret);
+    }
+    // Без знания пароля не вывести те же ключи, даже перехватив offer
+    if let Some(pake_key) = pake_key {
+        shared.extend_from_slice(&pake_key.key);
+    }
+
+    // ----- разделение ключей по направлениям -----
+    // Получаем 64 байта из HKDF для двух ключей
+    let salt = previous.map(|prev| &prev.rekey_secret.key[..]);
+    let hk = Hkdf::<Sha256>::new(salt, &shared);
+    // Третий ключ - корневой ключ для Double Ratchet, четвёртый - для следующего обмена,
+    // пятый - для подтверждения ключей
+    let mut okm = [0u8; 160];
+    let info = [&b"ssc-chat"[..], &transcript[..]].concat();
+    hk.expand(&info, &mut okm).unwrap();
+
+    // Очищаем shared сразу после использования
+    shared.zeroize();
+
+    let (k1, rest) = okm.split_at(32);
+    let (k2, rest) = rest.split_at(32);
+    let (root, rest) = rest.split_at(32);
+    let (rekey, confirm) = rest.split_at(32);
+
+    // Детерминированно выбираем ключи на основе публичных ключей
+    let (send_key_slice, recv_key_slice) = if my_pub < *peer_pub {
+        (k1, k2)
+    } else {
+        (k2, k1)
+    };
+
+    // Копируем ключи в массивы
+    let mut send_key = [0u8; 32];
+    let mut recv_key = [0u8; 32];
+    send_key.copy_from_slice(send_key_slice);
+    recv_key.copy_from_slice(recv_key_slice);
+
+    // ----- SAS на основе первого ключа -----
+    // При повторном обмене SAS не меняется: ключи привязаны к предыдущему контексту
+    let sas_bytes = match previous {
+        Some(prev) => prev.sas_bytes,
+        None => {
+            // k1 выведен с транскриптом в info, поэтому SAS покрывает и DTLS, и X25519
+            let fp_raw = Sha256::digest(k1);
+            let mut sas_bytes = [0u8; SAS_LEN];
+            sas_bytes.copy_from_slice(&fp_raw[..SAS_LEN]); // PARANOID mode: 48 bits
+            sas_bytes
+        }
+    };
+    let sas = sas::render(&sas_bytes, SasFormat::Hex);
+
+    let ratchet = if suite == CipherSuite::DoubleRatchet {
+        let mut root_key = [0u8; 32];
+        root_key.copy_from_slice(root);
+        let dr = DoubleRatchet::new(my_pub < *peer_pub, root_key, send_key, recv_key);
+        root_key.zeroize();
+        RatchetState::Double(dr.ok_or_else(|| SscError::Crypto("random generator failure".into()))?)
+    } else {
+        // Начальные ключи цепочек; дальше ключи выводятся храповиком
+        RatchetState::Symmetric {
+            send: SendChain::new(ZeroizedKey::new(send_key)),
+            recv: RecvChain::new(ZeroizedKey::new(recv_key)),
+        }
+    };
+
+    let mut rekey_secret = [0u8; 32];
+    rekey_secret.copy_from_slice(rekey);
+    let mut confirm_key = [0u8; 32];
+    confirm_key.copy_from_slice(confirm);
+
+    // Очищаем временные копии ключей
+    send_key.zeroize();
+    recv_key.zeroize();
+    okm.zeroize();
+
+    Ok(CryptoCtx {
+        ratchet,
+        sas,
+        sas_bytes,
+        verified: false,
+        pake: pake_key.is_some() || previous.is_some_and(|prev| prev.pake),
+        sent: 0,
+        rekey_secret: ZeroizedKey::new(rekey_secret),
+        confirm_key: ZeroizedKey::new(confirm_key),
+        transcript: *transcript,
+        is_low: my_pub < *peer_pub,
+    })
+}
+
+impl CryptoCtx {
+    /// SAS в выбранном представлении (одни и те же 48 бит во всех форматах)
+    pub fn sas_as(&self, format: SasFormat) -> String {
+        sas::render(&self.sas_bytes, format)
+    }
+
+    /// HMAC транскрипта с меткой стороны, чтобы тег нельзя было отразить обратно
+    fn confirmation_input(&self, low_side: bool) -> Vec<u8> {
+        let label: &[u8] = if low_side {
+            b"ssc-confirm-low"
+        } else {
+            b"ssc-confirm-high"
+        };
+        [label, &self.transcript[..]].concat()
+    }
+
+    /// Наш тег подтверждения ключей
+    pub fn confirmation_tag(&self) -> Vec<u8> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.confirm_key.key);
+        hmac::sign(&key, &self.confirmation_input(self.is_low))
+            .as_ref()
+            .to_vec()
+    }
+
+    /// Проверка тега подтверждения собеседника (в постоянное время)
+    pub fn verify_confirmation(&self, tag: &[u8]) -> bool {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.confirm_key.key);
+        hmac::verify(&key, &self.confirmation_input(!self.is_low), tag).is_ok()
+    }
+
+    /// Шифрует сообщение, возвращает (эпоха, номер, данные для отправки)
+    ///
+    /// После `NONCE_LIMIT` сообщений отказывает, пока не будет выполнен новый обмен ключами.
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<(u32, u64, Vec<u8>), SealError> {
+        let limit = *NONCE_LIMIT.lock().unwrap();
+        if self.sent >= limit {
+            return Err(SealError::Exhausted(self.sent));
+        }
+
+        let sealed = self.seal_inner(plaintext).ok_or(SealError::Encrypt)?;
+        self.sent += 1;
+        Ok(sealed)
+    }
+
+    fn seal_inner(&mut self, plaintext: &[u8]) -> Option<(u32, u64, Vec<u8>)> {
+        match &mut self.ratchet {
+            RatchetState::Symmetric { send, .. } => {
+                // `epoch (4 байта BE) || seq (8 байт BE) || ciphertext`
+                let (epoch, seq, ciphertext) = send.seal(plaintext)?;
+                let mut out = Vec::with_capacity(EPOCH_LEN + SEQ_LEN + ciphertext.len());
+                out.extend_from_slice(&epoch.to_be_bytes());
+                out.extend_from_slice(&seq.to_be_bytes());
+                out.extend_from_slice(&ciphertext);
+                Some((epoch, seq, out))
+            }
+            RatchetState::Double(dr) => dr.seal(plaintext),
+        }
+    }
+
+    /// Расшифровывает сообщение, возвращает (эпоха, номер, открытый текст)
+    pub fn open(&mut self, data: &[u8]) -> Result<(u32, u64, Vec<u8>), OpenError> {
+        match &mut self.ratchet {
+            RatchetState::Symmetric { recv, .. } => {
+                if data.len() < EPOCH_LEN + SEQ_LEN + TAG_LEN {
+                    return Err(OpenError::TooShort(data.len()));
+                }
+
+                let (epoch_bytes, rest) = data.split_at(EPOCH_LEN);
+                let (seq_bytes, ciphertext) = rest.split_at(SEQ_LEN);
+                let epoch = u32::from_be_bytes(epoch_bytes.try_into().unwrap());
+                let seq = u64::from_be_bytes(seq_bytes.try_into().unwrap());
+
+                let plaintext = recv.open(epoch, seq, ciphertext)?;
+                Ok((epoch, seq, plaintext))
+            }
+            RatchetState::Double(dr) => dr.open(data),
+        }
+    }
+
+    /// Текущая эпоха приёма (для логов)
+    pub fn recv_epoch(&self) -> u32 {
+        match &self.ratchet {
+            RatchetState::Symmetric { recv, .. } => recv.epoch(),
+            RatchetState::Double(dr) => dr.steps(),
+        }
+    }
+}
+
+/// Преобразование (epoch, seq) в nonce
+pub fn make_nonce(epoch: u32, seq: u64) -> Nonce<ChaCha20Poly1305> {
+    let mut b = [0u8; 12];
+    b[..4].copy_from_slice(&epoch.to_be_bytes());
+    b[4..].copy_from_slice(&seq.to_be_bytes());
+    *Nonce::<ChaCha20Poly1305>::from_slice(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_window_rejects_zero_and_duplicates() {
+        let mut window = ReplayWindow::default();
+        assert!(!window.check(0));
+        assert!(window.check(1));
+        window.accept(1);
+        assert!(!window.check(1));
+        assert!(window.check(2));
+    }
+
+    #[test]
+    fn replay_window_accepts_out_of_order_inside_window() {
+        let mut window = ReplayWindow::default();
+        window.accept(10);
+        assert!(window.check(5));
+        window.accept(5);
+        assert!(!window.check(5));
+        assert!(window.check(9));
+        assert!(!window.check(10));
+    }
+
+    #[test]
+    fn replay_window_rejects_numbers_behind_window() {
+        let mut window = ReplayWindow::default();
+        window.accept(REPLAY_WINDOW_SIZE + 10);
+        assert!(!window.check(10));
+        assert!(window.check(11));
+
+        // Большой скачок вперёд сбрасывает окно
+        window.accept(10 * REPLAY_WINDOW_SIZE);
+        assert!(!window.check(REPLAY_WINDOW_SIZE + 10));
+        assert!(!window.check(10 * REPLAY_WINDOW_SIZE));
+        assert!(window.check(10 * REPLAY_WINDOW_SIZE - 1));
+    }
+
+    #[test]
+    fn check_does_not_change_window() {
+        let window = ReplayWindow::default();
+        assert!(window.check(7));
+        assert!(window.check(7));
+    }
+}
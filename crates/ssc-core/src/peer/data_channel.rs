@@ -0,0 +1,977 @@
+use crate::events::{
+    emit_connected, emit_disconnected, emit_identity_changed, emit_incompatible_peer,
+    emit_key_confirmation_failed, emit_message, emit_rekey_failed,
+};
+use crate::logger::{KeyMaterial, Plaintext};
+use crate::peer::crypto::{
+    build_ctx, dtls_fingerprints, generate_keypair, transcript_hash, OpenError, SealError,
+    TranscriptSide,
+};
+use crate::peer::identity::{
+    check_pinned, sign_ephemeral, verify_claim, IdentityChanged, IdentityClaim, IdentityState,
+    PeerIdentity, PinStatus,
+};
+use crate::peer::kem::{self, PendingKem, ENCAPSULATION_KEY_LEN};
+use crate::peer::negotiation::{negotiate, Hello, IncompatiblePeer};
+use crate::peer::session::Session;
+use crate::peer::state::{
+    EPOCH_LEN, IDENTITY_ENABLED, MAX_QUEUED_MESSAGES, REKEY_TIMEOUT, SEQ_LEN, TAG_LEN,
+};
+use crate::peer::traffic::{
+    framing_enabled, restart_cover_traffic, stop_cover_traffic, unframe, InnerKind,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+use webrtc::data_channel::RTCDataChannel;
+use zeroize::Zeroizing;
+
+// ========== WIRE ENVELOPE ==========
+
+/// Версия формата кадра data channel
+pub const WIRE_VERSION: u8 = 1;
+
+/// Заголовок кадра: version (1) | type (1) | seq (8 BE) | len (4 BE)
+pub const ENVELOPE_HEADER_LEN: usize = 14;
+
+/// Первый кадр старой версии без конверта: голый публичный X25519-ключ
+pub const LEGACY_PUB_KEY_LEN: usize = 32;
+
+/// Тип кадра
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageType {
+    /// Публичный X25519-ключ (первичный и повторный обмен), в гибридном режиме и ключ ML-KEM
+    Handshake = 1,
+    /// Тег подтверждения ключей
+    KeyConfirm = 2,
+    /// Зашифрованное сообщение чата
+    ChatText = 3,
+    /// Служебные команды
+    Control = 4,
+    /// Подтверждение доставки кадра (payload - seq кадра, 8 байт BE)
+    Ack = 5,
+    /// Поддерживаемые версии и наборы алгоритмов (JSON), первый кадр соединения
+    Hello = 6,
+    /// Шифротекст ML-KEM к ключу инкапсуляции собеседника (гибридный режим)
+    KemCiphertext = 7,
+}
+
+impl TryFrom<u8> for MessageType {
+    type Error = EnvelopeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Handshake),
+            2 => Ok(Self::KeyConfirm),
+            3 => Ok(Self::ChatText),
+            4 => Ok(Self::Control),
+            5 => Ok(Self::Ack),
+            6 => Ok(Self::Hello),
+            7 => Ok(Self::KemCiphertext),
+            other => Err(EnvelopeError::UnknownType(other)),
+        }
+    }
+}
+
+/// Причина отказа в разборе кадра
+#[derive(Debug)]
+pub enum EnvelopeError {
+    TooShort(usize),
+    UnsupportedVersion(u8),
+    UnknownType(u8),
+    LengthMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    /// Кадр без конверта от собеседника старой версии
+    Legacy,
+}
+
+impl std::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooShort(len) => write!(f, "frame too short: {} bytes", len),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported wire version {}", v),
+            Self::UnknownType(t) => write!(f, "unknown message type {}", t),
+            Self::LengthMismatch { declared, actual } => write!(
+                f,
+                "payload length mismatch: declared {}, actual {}",
+                declared, actual
+            ),
+            Self::Legacy => write!(f, "unversioned frame from a legacy peer"),
+        }
+    }
+}
+
+/// Кадр data channel
+#[derive(Debug)]
+pub struct Envelope {
+    pub kind: MessageType,
+    pub seq: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENVELOPE_HEADER_LEN + self.payload.len());
+        out.push(WIRE_VERSION);
+        out.push(self.kind as u8);
+        out.extend_from_slice(&self.seq.to_be_bytes());
+        out.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    /// Разбирает кадр; голый 32-байтовый ключ старой версии - `EnvelopeError::Legacy`
+    pub fn decode(data: &[u8]) -> Result<Self, EnvelopeError> {
+        match Self::decode_versioned(data) {
+            Err(_) if data.len() == LEGACY_PUB_KEY_LEN => Err(EnvelopeError::Legacy),
+            result => result,
+        }
+    }
+
+    fn decode_versioned(data: &[u8]) -> Result<Self, EnvelopeError> {
+        if data.len() < ENVELOPE_HEADER_LEN {
+            return Err(EnvelopeError::TooShort(data.len()));
+        }
+        if data[0] != WIRE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(data[0]));
+        }
+        let kind = MessageType::try_from(data[1])?;
+        let seq = u64::from_be_bytes(data[2..10].try_into().unwrap());
+        let declared = u32::from_be_bytes(data[10..14].try_into().unwrap()) as usize;
+        let payload = &data[ENVELOPE_HEADER_LEN..];
+        if payload.len() != declared {
+            return Err(EnvelopeError::LengthMismatch {
+                declared,
+                actual: payload.len(),
+            });
+        }
+
+        Ok(Self {
+            kind,
+            seq,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// общий обработчик data-channel
+///
+/// Обработчики держат слабую ссылку на сессию: data channel хранится в ней же.
+pub fn attach_dc(session: &Arc<Session>, dc: &Arc<RTCDataChannel>) {
+    debug!(
+        "attach_dc called for session {} - clearing old state",
+        session.id
+    );
+
+    // отменяем отложенный disconnect, если он был
+    if let Some(handle) = session.disconnect_task.lock().unwrap().take() {
+        info!("Aborting pending disconnect task in attach_dc");
+        handle.abort();
+    }
+
+    // Очищаем старое состояние перед созданием нового соединения
+    stop_cover_traffic(session);
+    debug!("Clearing crypto context in attach_dc");
+    session.clear_keys();
+    *session.send_frame_seq.lock().unwrap() = 0;
+    session.clear_candidates();
+
+    {
+        *session.data_ch.lock().unwrap() = Some(dc.clone());
+    }
+
+    // Генерируем ключи сразу при создании data channel
+    let my_pub = generate_keypair(session);
+    debug!("Generated pub key: {}", KeyMaterial(&my_pub));
+
+    // Фиксируем Hello заранее: он войдёт в транскрипт в том виде, как ушёл в канал
+    let hello = serde_json::to_vec(&session.offered_hello).unwrap();
+    *session.local_hello.lock().unwrap() = Some(hello.clone());
+
+    // Отправляем Hello когда data channel открыт, pub-key - после согласования
+    let weak = Arc::downgrade(session);
+    dc.on_open(Box::new(move || {
+        info!("Data channel opened, sending hello...");
+        if let Some(session) = weak.upgrade() {
+            tokio::spawn(async move {
+                let sent = send_frame(&session, MessageType::Hello, hello).await;
+                debug!("Sent hello, result: {}", sent);
+            });
+        }
+        Box::pin(async {})
+    }));
+
+    let weak = Arc::downgrade(session);
+    dc.on_message(Box::new(move |msg| {
+        debug!("Received message, length: {}", msg.data.len());
+        let Some(session) = weak.upgrade() else {
+            return Box::pin(async {});
+        };
+
+        let envelope = match Envelope::decode(&msg.data) {
+            Ok(envelope) => envelope,
+            Err(EnvelopeError::Legacy) => {
+                // Старая версия сразу шлёт ключ и Hello не ждёт: без события UI бы завис
+                warn!(
+                    "Peer of session {} speaks the unversioned protocol",
+                    session.id
+                );
+                emit_incompatible_peer(&session, &IncompatiblePeer::legacy(&session.offered_hello));
+                return Box::pin(async {});
+            }
+            Err(e) => {
+                warn!("Rejected frame: {}", e);
+                return Box::pin(async {});
+            }
+        };
+
+        match envelope.kind {
+            MessageType::Hello => {
+                if handle_hello(&session, &envelope.payload) {
+                    let my_pub = *session.my_pub.lock().unwrap();
+                    let early = session.early_handshake.lock().unwrap().take();
+                    return Box::pin(async move {
+                        if let Some(my_pub) = my_pub {
+                            send_pub_key(&session, my_pub).await;
+                        }
+                        // Собеседник мог отправить ключ, не дождавшись нашего Hello
+                        if let Some(payload) = early {
+                            debug!("Processing handshake frame received before hello");
+                            receive_handshake(&session, &payload).await;
+                        }
+                    });
+                }
+                Box::pin(async {})
+            }
+            MessageType::Handshake => {
+                if session.negotiated.lock().unwrap().is_none() {
+                    // Hello собеседника ещё в пути: ключ дождётся согласования
+                    debug!("Handshake frame arrived before hello, deferring");
+                    *session.early_handshake.lock().unwrap() = Some(envelope.payload);
+                    return Box::pin(async {});
+                }
+                Box::pin(async move { receive_handshake(&session, &envelope.payload).await })
+            }
+            MessageType::KemCiphertext => {
+                Box::pin(async move { handle_kem_ciphertext(&session, envelope.payload).await })
+            }
+            MessageType::KeyConfirm => {
+                handle_key_confirmation(&session, &envelope.payload);
+                Box::pin(async {})
+            }
+            MessageType::ChatText => {
+                if handle_chat_text(&session, &envelope.payload) {
+                    // Подтверждаем доставку номером кадра отправителя
+                    let ack = envelope.seq.to_be_bytes().to_vec();
+                    return Box::pin(async move {
+                        send_frame(&session, MessageType::Ack, ack).await;
+                    });
+                }
+                Box::pin(async {})
+            }
+            MessageType::Ack => {
+                match <[u8; 8]>::try_from(&envelope.payload[..]) {
+                    Ok(seq) => debug!("Peer acknowledged frame {}", u64::from_be_bytes(seq)),
+                    Err(_) => warn!("Rejected malformed ack frame"),
+                }
+                Box::pin(async {})
+            }
+            MessageType::Control => {
+                debug!(
+                    "Ignoring control frame {} ({} bytes): no handler",
+                    envelope.seq,
+                    envelope.payload.len()
+                );
+                Box::pin(async {})
+            }
+        }
+    }));
+
+    let weak = Arc::downgrade(session);
+    dc.on_close(Box::new(move || {
+        info!("Data channel closed - emitting disconnected");
+        if let Some(session) = weak.upgrade() {
+            emit_disconnected(&session);
+        }
+        Box::pin(async {})
+    }));
+}
+
+/// Ключ собеседника после согласования версии и набора алгоритмов
+async fn receive_handshake(session: &Session, payload: &[u8]) {
+    let Some(negotiated) = *session.negotiated.lock().unwrap() else {
+        warn!("Rejected handshake frame: protocol not negotiated yet");
+        return;
+    };
+    let hybrid = negotiated.key_exchange.is_hybrid();
+    let Some(handshake) = parse_handshake(payload, hybrid) else {
+        return;
+    };
+    debug!("Received pub key: {}", KeyMaterial(&handshake.public));
+    handle_pub_key(session, handshake).await
+}
+
+/// Согласование версии и набора алгоритмов по Hello собеседника
+///
+/// Возвращает true, если можно переходить к обмену ключами.
+fn handle_hello(session: &Session, payload: &[u8]) -> bool {
+    if session.negotiated.lock().unwrap().is_some() {
+        debug!("Ignoring repeated hello frame");
+        return false;
+    }
+
+    let remote: Hello = match serde_json::from_slice(payload) {
+        Ok(hello) => hello,
+        Err(e) => {
+            warn!("Rejected malformed hello frame: {}", e);
+            return false;
+        }
+    };
+    let Some(local) = session
+        .local_hello
+        .lock()
+        .unwrap()
+        .as_deref()
+        .and_then(|raw| serde_json::from_slice::<Hello>(raw).ok())
+    else {
+        warn!("No local hello to negotiate against");
+        return false;
+    };
+
+    match negotiate(&local, &remote) {
+        Ok(negotiated) => {
+            info!(
+                "Negotiated protocol version {} with suite {} and key exchange {}",
+                negotiated.version,
+                negotiated.suite.as_str(),
+                negotiated.key_exchange.as_str()
+            );
+            *session.remote_hello.lock().unwrap() = Some(payload.to_vec());
+            *session.negotiated.lock().unwrap() = Some(negotiated);
+            true
+        }
+        Err(details) => {
+            emit_incompatible_peer(session, &details);
+            false
+        }
+    }
+}
+
+/// Расшифровка сообщения чата, возвращает true при успехе
+///
+/// Холостые сообщения тоже считаются успешными: подтверждение доставки
+/// уходит на любое сообщение, иначе по ним холостые можно было бы отличить.
+fn handle_chat_text(session: &Session, data: &[u8]) -> bool {
+    let framed = framing_enabled(session);
+    let mut lock = session.crypto.lock().unwrap();
+    let Some(ref mut ctx) = *lock else {
+        warn!("No crypto context available for message decryption");
+        return false;
+    };
+
+    match ctx.open(data) {
+        Ok((epoch, seq, plaintext)) => {
+            let text = if framed {
+                match unframe(&plaintext) {
+                    Some((InnerKind::Text, body)) => body,
+                    Some((InnerKind::Cover, _)) => return true,
+                    None => {
+                        warn!("Rejected malformed inner frame seq {}", seq);
+                        return false;
+                    }
+                }
+            } else {
+                &plaintext[..]
+            };
+            let plain = String::from_utf8_lossy(text).to_string();
+            debug!(
+                "Decrypted message epoch {} seq {}: {}",
+                epoch,
+                seq,
+                Plaintext(&plain)
+            );
+            emit_message(session, &plain);
+            return true;
+        }
+        Err(OpenError::TooShort(len)) => {
+            warn!(
+                "Message too short: {} < {}",
+                len,
+                EPOCH_LEN + SEQ_LEN + TAG_LEN
+            );
+        }
+        Err(OpenError::Replay(seq)) => {
+            warn!(
+                "Replay attack detected: seq {} already accepted or outside window",
+                seq
+            );
+        }
+        Err(OpenError::Decrypt(seq)) => {
+            warn!("Failed to decrypt message with seq {}", seq);
+        }
+        Err(OpenError::UnknownEpoch(epoch)) => {
+            warn!(
+                "Rejected message from epoch {} (current recv epoch {})",
+                epoch,
+                ctx.recv_epoch()
+            );
+        }
+        Err(OpenError::SkipLimit(seq)) => {
+            warn!(
+                "Rejected message with seq {}: too many skipped messages",
+                seq
+            );
+        }
+    }
+    false
+}
+
+/// Разобранный кадр Handshake
+struct HandshakeFrame {
+    public: [u8; 32],
+    kem_key: Option<Vec<u8>>, // ключ инкапсуляции ML-KEM, только в гибридном режиме
+    identity: Option<PeerIdentity>,
+}
+
+/// Разбор кадра Handshake: `pub (32) || [ML-KEM ek (1184)] || [IdentityClaim JSON]`
+///
+/// Ключ ML-KEM обязателен, если согласован гибридный обмен.
+/// Если подпись ключом идентичности приложена, она обязана быть верной.
+fn parse_handshake(payload: &[u8], hybrid: bool) -> Option<HandshakeFrame> {
+    let fixed_len = if hybrid {
+        32 + ENCAPSULATION_KEY_LEN
+    } else {
+        32
+    };
+    if payload.len() < fixed_len {
+        warn!(
+            "Rejected handshake frame with {} byte payload",
+            payload.len()
+        );
+        return None;
+    }
+    let (public, rest) = payload.split_at(32);
+    let peer_pub = <[u8; 32]>::try_from(public).unwrap();
+    let (kem_key, claim) = rest.split_at(fixed_len - 32);
+    let kem_key = hybrid.then(|| kem_key.to_vec());
+    if claim.is_empty() {
+        return Some(HandshakeFrame {
+            public: peer_pub,
+            kem_key,
+            identity: None,
+        });
+    }
+
+    let Ok(claim) = serde_json::from_slice::<IdentityClaim>(claim) else {
+        warn!("Rejected handshake frame: malformed identity claim");
+        return None;
+    };
+    let Some(identity) = verify_claim(&claim, &peer_pub) else {
+        warn!("Rejected handshake frame: identity signature does not verify");
+        return None;
+    };
+    info!(
+        "Peer ephemeral key signed by identity {}",
+        identity.fingerprint()
+    );
+    Some(HandshakeFrame {
+        public: peer_pub,
+        kem_key,
+        identity: Some(identity),
+    })
+}
+
+/// Обработка публичного ключа собеседника: первичный или повторный обмен ключами
+///
+/// В гибридном режиме сначала инкапсулируем секрет к ключу ML-KEM собеседника
+/// и ждём его шифротекст; иначе сразу выводим ключи.
+async fn handle_pub_key(session: &Session, handshake: HandshakeFrame) {
+    let HandshakeFrame {
+        public: peer_pub,
+        kem_key,
+        identity,
+    } = handshake;
+
+    // Контекст уже есть - это повторный обмен ключами
+    let renegotiation = session.crypto.lock().unwrap().is_some();
+    if renegotiation {
+        info!("Crypto context already exists, renegotiating keys");
+
+        // Ключ идентичности не может смениться посреди сессии
+        if *session.remote_identity.lock().unwrap() != identity {
+            warn!("Rejected key renegotiation: peer identity differs from the session identity");
+            return;
+        }
+
+        // Обмен начал собеседник - отвечаем своим новым ключом
+        if session.my_priv.lock().unwrap().is_none() {
+            let my_pub = generate_keypair(session);
+            send_pub_key(session, my_pub).await;
+        }
+    }
+
+    if !renegotiation {
+        // Пакет сигнализации подписан ключом идентичности - ключ без той же подписи
+        // означает подмену или попытку обойти закреплённый контакт
+        let expected = session.bundle_identity.lock().unwrap().clone();
+        if let Some(expected) = expected.filter(|e| identity.as_ref() != Some(e)) {
+            warn!(
+                "Rejected handshake: bundle was signed by identity {}, handshake is not, closing session",
+                expected.fingerprint()
+            );
+            session.close().await;
+            return;
+        }
+        *session.remote_identity.lock().unwrap() = identity;
+    }
+
+    let Some(kem_key) = kem_key else {
+        derive_keys(session, peer_pub, None).await;
+        return;
+    };
+    let Some((ciphertext, outgoing)) = kem::encapsulate(&kem_key) else {
+        warn!("Rejected handshake: invalid ML-KEM encapsulation key");
+        return;
+    };
+    *session.pending_kem.lock().unwrap() = Some(PendingKem { peer_pub, outgoing });
+    let sent = send_frame(session, MessageType::KemCiphertext, ciphertext).await;
+    debug!("Sent ML-KEM ciphertext, result: {}", sent);
+}
+
+/// Шифротекст ML-KEM собеседника: завершаем гибридный обмен
+async fn handle_kem_ciphertext(session: &Session, ciphertext: Vec<u8>) {
+    let Some(pending) = session.pending_kem.lock().unwrap().take() else {
+        warn!("Rejected ML-KEM ciphertext: no hybrid key exchange in progress");
+        return;
+    };
+    let incoming = {
+        let dk = session.my_kem.lock().unwrap().take();
+        dk.and_then(|dk| kem::decapsulate(&dk, &ciphertext))
+    };
+    let Some(incoming) = incoming else {
+        warn!("Rejected ML-KEM ciphertext: decapsulation failed");
+        return;
+    };
+    let Some(my_pub) = *session.my_pub.lock().unwrap() else {
+        warn!("No local pub key for hybrid key exchange");
+        return;
+    };
+
+    // Секреты в каноническом порядке: сначала инкапсулированный к стороне с меньшим ключом
+    let (first, second) = if my_pub < pending.peer_pub {
+        (&incoming, &pending.outgoing)
+    } else {
+        (&pending.outgoing, &incoming)
+    };
+    let kem_secret = Zeroizing::new([first.key, second.key].concat());
+    info!("Hybrid ML-KEM exchange completed");
+    derive_keys(session, pending.peer_pub, Some(&kem_secret)).await;
+}
+
+/// Вывод ключей сессии и отправка тега подтверждения
+async fn derive_keys(session: &Session, peer_pub: [u8; 32], kem_secret: Option<&[u8]>) {
+    let renegotiation = session.crypto.lock().unwrap().is_some();
+
+    let Some(transcript) = session_transcript(session, &peer_pub).await else {
+        warn!("Cannot bind key exchange to DTLS fingerprints, aborting handshake");
+        return;
+    };
+
+    let Some(negotiated) = *session.negotiated.lock().unwrap() else {
+        warn!("No negotiated cipher suite, aborting handshake");
+        return;
+    };
+
+    let ctx = {
+        let current = session.crypto.lock().unwrap();
+        let pake_key = session.pake_key.lock().unwrap();
+        build_ctx(
+            session,
+            &peer_pub,
+            &transcript,
+            negotiated.suite,
+            pake_key.as_ref(),
+            kem_secret,
+            current.as_ref(),
+        )
+    };
+    let ctx = match ctx {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            warn!("Aborting handshake: {}", e);
+            return;
+        }
+    };
+    if !renegotiation {
+        info!("SAS generated: {}", ctx.sas);
+    }
+
+    // Отправляем свой тег подтверждения и ждём тег собеседника
+    let tag = ctx.confirmation_tag();
+    *session.pending_crypto.lock().unwrap() = Some(ctx);
+    info!("Crypto context derived, sending key confirmation");
+    send_frame(session, MessageType::KeyConfirm, tag).await;
+}
+
+/// Проверка подтверждения ключей; только после неё контекст становится активным
+fn handle_key_confirmation(session: &Arc<Session>, tag: &[u8]) {
+    let Some(ctx) = session.pending_crypto.lock().unwrap().take() else {
+        warn!("No pending crypto context for key confirmation");
+        return;
+    };
+
+    if !ctx.verify_confirmation(tag) {
+        // ctx удаляется здесь, ключи зануляются
+        warn!("Key confirmation failed - peer derived different keys, closing session");
+        emit_key_confirmation_failed(session);
+        // С расходящимися ключами сессия не заработает: закрываем, а не оставляем в Connecting
+        let session = session.clone();
+        tokio::spawn(async move { session.close().await });
+        return;
+    }
+
+    let renegotiation = {
+        let mut current = session.crypto.lock().unwrap();
+        let mut ctx = ctx;
+        ctx.verified = match current.as_ref() {
+            // SAS при повторном обмене не меняется, поэтому подтверждение сохраняется
+            Some(c) => c.verified,
+            // Пароль CPace уже доказал, что на другой стороне нужный собеседник
+            None => pinned_identity_verified(session) || ctx.pake,
+        };
+        current.replace(ctx).is_some()
+    };
+    if renegotiation {
+        info!("Key confirmation succeeded, crypto context renegotiated");
+        if let Some(handle) = session.rekey_task.lock().unwrap().take() {
+            handle.abort();
+        }
+        if !session.outbox.lock().unwrap().is_empty() {
+            let session = session.clone();
+            tokio::spawn(async move { flush_outbox(&session).await });
+        }
+        return;
+    }
+
+    // Всегда отправляем событие подключения после установки криптографического контекста
+    info!("Key confirmation succeeded, sending connected event");
+
+    // Проверим, что fingerprint доступен сразу после создания контекста
+    let _test_fp = session
+        .crypto
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|c| c.sas.clone());
+    debug!(
+        "Fingerprint immediately after context creation: {:?}",
+        _test_fp
+    );
+
+    // Отправляем событие подключения
+    info!("Sending ssc-connected event immediately");
+    emit_connected(session);
+    restart_cover_traffic(session);
+}
+
+/// Сверка идентичности собеседника с закреплёнными контактами
+///
+/// Возвращает true, если этот ключ идентичности уже подтверждался по SAS;
+/// результат сверки сохраняется в `identity_state` для списка сессий.
+fn pinned_identity_verified(session: &Session) -> bool {
+    let Some(peer) = session.remote_identity.lock().unwrap().clone() else {
+        return false;
+    };
+
+    let (state, verified) = match check_pinned(&peer) {
+        PinStatus::New => {
+            info!(
+                "Identity {} is not pinned, SAS verification required",
+                peer.fingerprint()
+            );
+            (IdentityState::Unknown, false)
+        }
+        PinStatus::Known { verified } => {
+            info!(
+                "Known identity {}, verified earlier: {}",
+                peer.fingerprint(),
+                verified
+            );
+            let state = if verified {
+                IdentityState::Verified
+            } else {
+                IdentityState::Unknown
+            };
+            (state, verified)
+        }
+        PinStatus::Changed { previous } => {
+            emit_identity_changed(
+                &session.events,
+                Some(&session.id),
+                &IdentityChanged {
+                    installation_id: peer.installation_id.clone(),
+                    previous_fingerprint: previous.fingerprint,
+                    new_fingerprint: peer.fingerprint(),
+                },
+            );
+            (IdentityState::Changed, false)
+        }
+    };
+    *session.identity_state.lock().unwrap() = Some(state);
+    verified
+}
+
+/// Хэш транскрипта: X25519-ключи, DTLS fingerprint из local/remote SDP, оба Hello
+/// и ключи подписи обоих пакетов сигнализации
+async fn session_transcript(session: &Session, peer_pub: &[u8; 32]) -> Option<[u8; 32]> {
+    let my_pub = (*session.my_pub.lock().unwrap())?;
+    let local_hello = session.local_hello.lock().unwrap().clone()?;
+    let remote_hello = session.remote_hello.lock().unwrap().clone()?;
+    let (Some(local_signer), Some(remote_signer)) = (
+        *session.local_signer.lock().unwrap(),
+        *session.remote_signer.lock().unwrap(),
+    ) else {
+        warn!("Signaling bundle signer missing, cannot bind key exchange to it");
+        return None;
+    };
+    let pc = { session.peer.lock().unwrap().as_ref().cloned() }?;
+
+    let local = pc.local_description().await?;
+    let remote = pc.remote_description().await?;
+    let local_fp = dtls_fingerprints(&local.sdp);
+    let remote_fp = dtls_fingerprints(&remote.sdp);
+    if local_fp.is_empty() || remote_fp.is_empty() {
+        warn!("DTLS fingerprint missing in local or remote description");
+        return None;
+    }
+    debug!(
+        "Binding key exchange to DTLS fingerprints: local={} remote={}",
+        local_fp, remote_fp
+    );
+
+    Some(transcript_hash(
+        &TranscriptSide {
+            public: &my_pub,
+            fingerprint: &local_fp,
+            hello: &local_hello,
+            signer: &local_signer,
+        },
+        &TranscriptSide {
+            public: peer_pub,
+            fingerprint: &remote_fp,
+            hello: &remote_hello,
+            signer: &remote_signer,
+        },
+    ))
+}
+
+/// Отправка кадра по data channel сессии
+pub async fn send_frame(session: &Session, kind: MessageType, payload: Vec<u8>) -> bool {
+    let dc = { session.data_ch.lock().unwrap().as_ref().cloned() };
+    let Some(dc) = dc else {
+        warn!("No data channel available for sending {:?} frame", kind);
+        return false;
+    };
+
+    let seq = {
+        let mut counter = session.send_frame_seq.lock().unwrap();
+        *counter += 1;
+        *counter
+    };
+    let frame = Envelope { kind, seq, payload }.encode();
+    dc.send(&Bytes::from(frame)).await.is_ok()
+}
+
+/// Отправка публичного ключа по data channel сессии
+///
+/// Если включены ключи идентичности, к ключу прикладывается его подпись.
+/// В гибридном режиме вместе с ним уходит новый ключ инкапсуляции ML-KEM.
+async fn send_pub_key(session: &Session, my_pub: [u8; 32]) -> bool {
+    let mut payload = my_pub.to_vec();
+    let hybrid = session
+        .negotiated
+        .lock()
+        .unwrap()
+        .is_some_and(|n| n.key_exchange.is_hybrid());
+    if hybrid {
+        let (dk, encapsulation_key) = kem::generate();
+        *session.my_kem.lock().unwrap() = Some(dk);
+        payload.extend_from_slice(&encapsulation_key);
+    }
+    if *IDENTITY_ENABLED.lock().unwrap() {
+        match sign_ephemeral(&my_pub).and_then(|claim| serde_json::to_vec(&claim).ok()) {
+            Some(claim) => payload.extend_from_slice(&claim),
+            None => warn!("Identity key unavailable, sending unsigned pub key"),
+        }
+    }
+    let sent = send_frame(session, MessageType::Handshake, payload).await;
+    debug!("Sent pub key: {}, result: {}", KeyMaterial(&my_pub), sent);
+    sent
+}
+
+/// Запускает новый обмен ключами поверх открытого data channel сессии
+///
+/// Если собеседник не ответит за `REKEY_TIMEOUT`, сессия закрывается с событием
+/// `RekeyFailed`: старые ключи исчерпаны, а очередь не должна ждать вечно.
+pub async fn start_rekey(session: &Arc<Session>) -> bool {
+    if session.my_priv.lock().unwrap().is_some() {
+        info!("Key renegotiation already in progress");
+        return true;
+    }
+    info!("Starting key renegotiation");
+    let my_pub = generate_keypair(session);
+    if !send_pub_key(session, my_pub).await {
+        // Ключ не ушёл: следующая попытка начнёт обмен заново
+        *session.my_priv.lock().unwrap() = None;
+        *session.my_kem.lock().unwrap() = None;
+        return false;
+    }
+
+    let weak = Arc::downgrade(session);
+    let handle = tokio::spawn(async move {
+        sleep(REKEY_TIMEOUT).await;
+        let Some(session) = weak.upgrade() else {
+            return;
+        };
+        // Задача завершает себя сама: хэндл убирается без abort
+        session.rekey_task.lock().unwrap().take();
+        warn!(
+            "Peer did not answer key renegotiation within {:?}, closing session {}",
+            REKEY_TIMEOUT, session.id
+        );
+        emit_rekey_failed(&session);
+        session.close().await;
+    });
+    if let Some(previous) = session.rekey_task.lock().unwrap().replace(handle) {
+        previous.abort();
+    }
+    true
+}
+
+/// Ставит открытый текст в очередь до завершения нового обмена ключами
+///
+/// Возвращает длину очереди или `None`, если очередь заполнена.
+pub fn queue_until_rekey(session: &Session, plaintext: Vec<u8>) -> Option<usize> {
+    let mut outbox = session.outbox.lock().unwrap();
+    if outbox.len() >= MAX_QUEUED_MESSAGES {
+        return None;
+    }
+    outbox.push(Zeroizing::new(plaintext));
+    Some(outbox.len())
+}
+
+/// Отправляет сообщения из очереди новыми ключами в исходном порядке
+///
+/// Если лимит исчерпан снова, остаток очереди ждёт следующего обмена.
+async fn flush_outbox(session: &Arc<Session>) {
+    loop {
+        let sealed = {
+            let mut crypto = session.crypto.lock().unwrap();
+            let mut outbox = session.outbox.lock().unwrap();
+            let (Some(ctx), Some(plaintext)) = (crypto.as_mut(), outbox.first()) else {
+                return;
+            };
+            let sealed = ctx.seal(plaintext);
+            if sealed.is_ok() {
+                outbox.remove(0);
+            }
+            sealed
+        };
+
+        match sealed {
+            Ok((epoch, seq_num, ciphertext)) => {
+                debug!(
+                    "Sending queued message with epoch {} seq {}",
+                    epoch, seq_num
+                );
+                if !send_frame(session, MessageType::ChatText, ciphertext).await {
+                    warn!("Failed to send queued message");
+                }
+            }
+            Err(SealError::Exhausted(sent)) => {
+                info!("Message limit reached again after {} messages", sent);
+                start_rekey(session).await;
+                return;
+            }
+            Err(_) => {
+                warn!("Failed to encrypt queued messages, dropping them");
+                session.outbox.lock().unwrap().clear();
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_roundtrip() {
+        let envelope = Envelope {
+            kind: MessageType::ChatText,
+            seq: 42,
+            payload: b"ciphertext".to_vec(),
+        };
+        let decoded = Envelope::decode(&envelope.encode()).unwrap();
+        assert_eq!(decoded.kind, MessageType::ChatText);
+        assert_eq!(decoded.seq, 42);
+        assert_eq!(decoded.payload, b"ciphertext");
+    }
+
+    #[test]
+    fn bare_public_key_is_a_legacy_frame() {
+        for first in [0u8, WIRE_VERSION, 0xff] {
+            let mut key = [7u8; LEGACY_PUB_KEY_LEN];
+            key[0] = first;
+            assert!(matches!(Envelope::decode(&key), Err(EnvelopeError::Legacy)));
+        }
+    }
+
+    #[test]
+    fn envelope_of_legacy_length_still_decodes() {
+        let envelope = Envelope {
+            kind: MessageType::Handshake,
+            seq: 0,
+            payload: vec![1; LEGACY_PUB_KEY_LEN - ENVELOPE_HEADER_LEN],
+        };
+        let encoded = envelope.encode();
+        assert_eq!(encoded.len(), LEGACY_PUB_KEY_LEN);
+        assert_eq!(
+            Envelope::decode(&encoded).unwrap().kind,
+            MessageType::Handshake
+        );
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        assert!(matches!(
+            Envelope::decode(&[WIRE_VERSION; 3]),
+            Err(EnvelopeError::TooShort(3))
+        ));
+        let mut frame = Envelope {
+            kind: MessageType::Ack,
+            seq: 1,
+            payload: vec![0; 8],
+        }
+        .encode();
+        frame[0] = WIRE_VERSION + 1;
+        assert!(matches!(
+            Envelope::decode(&frame),
+            Err(EnvelopeError::UnsupportedVersion(_))
+        ));
+        frame[0] = WIRE_VERSION;
+        frame.pop();
+        assert!(matches!(
+            Envelope::decode(&frame),
+            Err(EnvelopeError::LengthMismatch {
+                declared: 8,
+                actual: 7
+            })
+        ));
+    }
+}
@@ -0,0 +1,434 @@
+use crate::peer::crypto::OpenError;
+use crate::peer::state::{MAX_SKIP, MAX_SKIPPED_KEYS, TAG_LEN};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Nonce, Payload},
+    ChaCha20Poly1305, Key,
+};
+use hkdf::Hkdf;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/// Длина заголовка сообщения: `dh (32) || pn (4 BE) || n (4 BE)`
+pub const DR_HEADER_LEN: usize = 40;
+
+type Key32 = Zeroizing<[u8; 32]>;
+
+/// KDF корневой цепочки: смешиваем свежий DH в корневой ключ
+fn kdf_rk(rk: &Key32, dh_out: &[u8]) -> (Key32, Key32) {
+    let hk = Hkdf::<Sha256>::new(Some(&rk[..]), dh_out);
+    let mut okm = Zeroizing::new([0u8; 64]);
+    hk.expand(b"ssc-dr-root", &mut okm[..]).unwrap();
+
+    let mut root = Zeroizing::new([0u8; 32]);
+    let mut chain = Zeroizing::new([0u8; 32]);
+    root.copy_from_slice(&okm[..32]);
+    chain.copy_from_slice(&okm[32..]);
+    (root, chain)
+}
+
+/// KDF цепочки сообщений: следующий ключ цепочки и ключ сообщения
+fn kdf_ck(ck: &Key32) -> (Key32, Key32) {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &ck[..]);
+
+    let mut next = Zeroizing::new([0u8; 32]);
+    let mut mk = Zeroizing::new([0u8; 32]);
+    next.copy_from_slice(hmac::sign(&key, &[0x02]).as_ref());
+    mk.copy_from_slice(hmac::sign(&key, &[0x01]).as_ref());
+    (next, mk)
+}
+
+/// Новая пара ключей для DH-храповика из того же CSPRNG, что и остальные ключи
+fn new_ratchet_key() -> Option<StaticSecret> {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    SystemRandom::new().fill(&mut bytes[..]).ok()?;
+    Some(StaticSecret::from(*bytes))
+}
+
+/// DH с проверкой на точки малого порядка
+fn dh(secret: &StaticSecret, public: &[u8; 32]) -> Option<Key32> {
+    let shared = secret.diffie_hellman(&PublicKey::from(*public));
+    if !shared.was_contributory() {
+        return None;
+    }
+    Some(Zeroizing::new(*shared.as_bytes()))
+}
+
+/// Отложенный ключ сообщения: ключ отправителя, номер сообщения и ключ
+///
+/// Ключ отправителя `None` - сообщение начальной цепочки, DH-ключ которой
+/// принимающей стороне неизвестен; такие ключи сопоставляются по тегу AEAD.
+type SkippedKey = (Option<[u8; 32]>, u32, Key32);
+
+/// Состояние Double Ratchet (симметричная инициализация)
+///
+/// Обе стороны стартуют с начальными цепочками из `build_ctx` и свежими
+/// DH-ключами. Первый DH-шаг делает только "Alice" (сторона с меньшим
+/// публичным ключом), после чего шаги идут по очереди, как в Signal.
+pub struct DoubleRatchet {
+    chains: Chains,
+    // Ключи пропущенных сообщений, старые вытесняются
+    skipped: VecDeque<SkippedKey>,
+}
+
+/// Корневой ключ, цепочки и счётчики без отложенных ключей
+///
+/// Копия небольшая и без выделений памяти: на ней идёт пробная расшифровка.
+#[derive(Clone)]
+struct Chains {
+    is_alice: bool,
+    rk: Key32,
+    dhs: StaticSecret,
+    dhr: Option<[u8; 32]>,
+    cks: Key32,
+    ckr: Key32,
+    ns: u32,
+    nr: u32,
+    pn: u32,
+    steps: u32, // количество DH-шагов, для логов
+}
+
+impl DoubleRatchet {
+    pub fn new(
+        is_alice: bool,
+        root: [u8; 32],
+        send_chain: [u8; 32],
+        recv_chain: [u8; 32],
+    ) -> Option<Self> {
+        Some(Self {
+            chains: Chains {
+                is_alice,
+                rk: Zeroizing::new(root),
+                dhs: new_ratchet_key()?,
+                dhr: None,
+                cks: Zeroizing::new(send_chain),
+                ckr: Zeroizing::new(recv_chain),
+                ns: 0,
+                nr: 0,
+                pn: 0,
+                steps: 0,
+            },
+            skipped: VecDeque::new(),
+        })
+    }
+
+    /// Количество выполненных DH-шагов
+    pub fn steps(&self) -> u32 {
+        self.chains.steps
+    }
+
+    /// Шифрует сообщение: на выходе `header || ciphertext`, заголовок входит в AAD
+    pub fn seal(&mut self, plaintext: &[u8]) -> Option<(u32, u64, Vec<u8>)> {
+        let chains = &mut self.chains;
+        let n = chains.ns;
+        chains.ns = chains.ns.checked_add(1)?;
+        let (ck, mk) = kdf_ck(&chains.cks);
+        chains.cks = ck;
+
+        let mut out = Vec::with_capacity(DR_HEADER_LEN + plaintext.len() + TAG_LEN);
+        out.extend_from_slice(PublicKey::from(&chains.dhs).as_bytes());
+        out.extend_from_slice(&chains.pn.to_be_bytes());
+        out.extend_from_slice(&n.to_be_bytes());
+
+        let cipher = ChaCha20Poly1305::new(&Key::from(*mk));
+        let ciphertext = cipher
+            .encrypt(
+                &Nonce::<ChaCha20Poly1305>::default(),
+                Payload {
+                    msg: plaintext,
+                    aad: &out,
+                },
+            )
+            .ok()?;
+        out.extend_from_slice(&ciphertext);
+        Some((chains.steps, n as u64, out))
+    }
+
+    /// Расшифровывает сообщение; состояние меняется только при успехе
+    ///
+    /// Пробная расшифровка идёт на копии цепочек, отложенные ключи не копируются.
+    /// Поддельный заголовок обходится не больше чем в `3 * MAX_SKIP` шагов KDF
+    /// и не меняет ни цепочки, ни хранилище отложенных ключей.
+    pub fn open(&mut self, data: &[u8]) -> Result<(u32, u64, Vec<u8>), OpenError> {
+        if data.len() < DR_HEADER_LEN + TAG_LEN {
+            return Err(OpenError::TooShort(data.len()));
+        }
+        let (header, ciphertext) = data.split_at(DR_HEADER_LEN);
+        let dh_pub: [u8; 32] = header[..32].try_into().unwrap();
+        let pn = u32::from_be_bytes(header[32..36].try_into().unwrap());
+        let n = u32::from_be_bytes(header[36..40].try_into().unwrap());
+
+        // ----- сообщение, для которого ключ был отложен -----
+        if let Some(plaintext) = self.open_skipped(&dh_pub, n, header, ciphertext) {
+            return Ok((self.chains.steps, n as u64, plaintext));
+        }
+
+        let mut trial = self.chains.clone();
+        let mut skipped = Vec::new();
+        let plaintext = trial.open(&dh_pub, pn, n, header, ciphertext, &mut skipped)?;
+
+        self.chains = trial;
+        self.skipped.extend(skipped);
+        // Ограничиваем хранилище: вытесняем самые старые ключи
+        while self.skipped.len() > MAX_SKIPPED_KEYS {
+            self.skipped.pop_front();
+        }
+        Ok((self.chains.steps, n as u64, plaintext))
+    }
+
+    /// Сообщение с отложенным ключом; ключ расходуется, только если тег сошёлся
+    fn open_skipped(
+        &mut self,
+        dh_pub: &[u8; 32],
+        n: u32,
+        header: &[u8],
+        ciphertext: &[u8],
+    ) -> Option<Vec<u8>> {
+        let (pos, plaintext) = self
+            .skipped
+            .iter()
+            .enumerate()
+            .filter(|(_, (sender, i, _))| *i == n && sender.as_ref().is_none_or(|k| k == dh_pub))
+            .find_map(|(pos, (_, _, mk))| {
+                decrypt(mk, header, ciphertext, n)
+                    .ok()
+                    .map(|plaintext| (pos, plaintext))
+            })?;
+        self.skipped.remove(pos);
+        Some(plaintext)
+    }
+}
+
+impl Chains {
+    /// Расшифровка с DH-шагом при новом ключе собеседника
+    ///
+    /// Ключи, пропущенные по пути, добавляются в `skipped`.
+    fn open(
+        &mut self,
+        dh_pub: &[u8; 32],
+        pn: u32,
+        n: u32,
+        header: &[u8],
+        ciphertext: &[u8],
+        skipped: &mut Vec<SkippedKey>,
+    ) -> Result<Vec<u8>, OpenError> {
+        match self.dhr {
+            None => {
+                // Первое сообщение от собеседника: обычно из начальной цепочки
+                let mut bootstrap = self.clone();
+                let mut bootstrap_skipped = Vec::new();
+                bootstrap.dhr = Some(*dh_pub);
+                if bootstrap.is_alice {
+                    // Alice запускает DH-храповик, как только узнала ключ Bob
+                    bootstrap.send_step(dh_pub, n)?;
+                }
+                match bootstrap.open_in_chain(header, ciphertext, n, &mut bootstrap_skipped) {
+                    Ok(plaintext) => {
+                        *self = bootstrap;
+                        skipped.append(&mut bootstrap_skipped);
+                        return Ok(plaintext);
+                    }
+                    Err(e) if self.is_alice => return Err(e),
+                    Err(_) => {}
+                }
+
+                // Bob: Alice уже сделала первый DH-шаг на нашем начальном ключе.
+                // Её начальные сообщения могут прийти позже, а их DH-ключ нам
+                // неизвестен, поэтому их ключи откладываются без ключа отправителя
+                self.skip_until(pn, None, skipped)?;
+                self.receive_step(dh_pub, n)?;
+                self.send_step(dh_pub, n)?;
+            }
+            Some(current) if current != *dh_pub => {
+                // Новый ключ собеседника: DH-шаг
+                self.skip_until(pn, Some(current), skipped)?;
+                self.receive_step(dh_pub, n)?;
+                self.send_step(dh_pub, n)?;
+            }
+            Some(_) => {}
+        }
+
+        self.open_in_chain(header, ciphertext, n, skipped)
+    }
+
+    /// Расшифровка в текущей принимающей цепочке с сохранением пропущенных ключей
+    fn open_in_chain(
+        &mut self,
+        header: &[u8],
+        ciphertext: &[u8],
+        n: u32,
+        skipped: &mut Vec<SkippedKey>,
+    ) -> Result<Vec<u8>, OpenError> {
+        if n < self.nr {
+            // Ключ уже использован и не отложен - повтор
+            return Err(OpenError::Replay(n as u64));
+        }
+        self.skip_until(n, self.dhr, skipped)?;
+
+        let (ck, mk) = kdf_ck(&self.ckr);
+        self.ckr = ck;
+        self.nr += 1;
+        decrypt(&mk, header, ciphertext, n)
+    }
+
+    /// Откладывает ключи сообщений принимающей цепочки до номера `until`
+    fn skip_until(
+        &mut self,
+        until: u32,
+        dh_pub: Option<[u8; 32]>,
+        skipped: &mut Vec<SkippedKey>,
+    ) -> Result<(), OpenError> {
+        if until <= self.nr {
+            return Ok(());
+        }
+        if until - self.nr > MAX_SKIP {
+            return Err(OpenError::SkipLimit(until as u64));
+        }
+        while self.nr < until {
+            let (ck, mk) = kdf_ck(&self.ckr);
+            self.ckr = ck;
+            skipped.push((dh_pub, self.nr, mk));
+            self.nr += 1;
+        }
+        Ok(())
+    }
+
+    /// Шаг приёма: новая принимающая цепочка от DH(наш ключ, новый ключ собеседника)
+    fn receive_step(&mut self, dh_pub: &[u8; 32], n: u32) -> Result<(), OpenError> {
+        let shared = dh(&self.dhs, dh_pub).ok_or(OpenError::Decrypt(n as u64))?;
+        let (rk, ckr) = kdf_rk(&self.rk, &shared[..]);
+        self.rk = rk;
+        self.ckr = ckr;
+        self.dhr = Some(*dh_pub);
+        self.nr = 0;
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Шаг отправки: свежий собственный ключ и новая отправляющая цепочка
+    fn send_step(&mut self, dh_pub: &[u8; 32], n: u32) -> Result<(), OpenError> {
+        self.dhs = new_ratchet_key().ok_or(OpenError::Decrypt(n as u64))?;
+        let shared = dh(&self.dhs, dh_pub).ok_or(OpenError::Decrypt(n as u64))?;
+        let (rk, cks) = kdf_rk(&self.rk, &shared[..]);
+        self.rk = rk;
+        self.cks = cks;
+        self.pn = self.ns;
+        self.ns = 0;
+        self.steps += 1;
+        Ok(())
+    }
+}
+
+/// AEAD-расшифровка одноразовым ключом сообщения
+fn decrypt(mk: &Key32, header: &[u8], ciphertext: &[u8], n: u32) -> Result<Vec<u8>, OpenError> {
+    ChaCha20Poly1305::new(&Key::from(**mk))
+        .decrypt(
+            &Nonce::<ChaCha20Poly1305>::default(),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| OpenError::Decrypt(n as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (DoubleRatchet, DoubleRatchet) {
+        let alice = DoubleRatchet::new(true, [1; 32], [2; 32], [3; 32]).unwrap();
+        let bob = DoubleRatchet::new(false, [1; 32], [3; 32], [2; 32]).unwrap();
+        (alice, bob)
+    }
+
+    fn seal(dr: &mut DoubleRatchet, text: &str) -> Vec<u8> {
+        dr.seal(text.as_bytes()).unwrap().2
+    }
+
+    fn open(dr: &mut DoubleRatchet, data: &[u8]) -> Result<String, OpenError> {
+        dr.open(data)
+            .map(|(_, _, plaintext)| String::from_utf8(plaintext).unwrap())
+    }
+
+    #[test]
+    fn messages_flow_both_ways_with_dh_steps() {
+        let (mut alice, mut bob) = pair();
+        for round in 0..3 {
+            let a = seal(&mut alice, &format!("a{round}"));
+            assert_eq!(open(&mut bob, &a).unwrap(), format!("a{round}"));
+            let b = seal(&mut bob, &format!("b{round}"));
+            assert_eq!(open(&mut alice, &b).unwrap(), format!("b{round}"));
+        }
+        assert!(alice.steps() > 0);
+        assert!(bob.steps() > 0);
+    }
+
+    #[test]
+    fn out_of_order_messages_use_skipped_keys() {
+        let (mut alice, mut bob) = pair();
+        let first = seal(&mut alice, "first");
+        let second = seal(&mut alice, "second");
+        let third = seal(&mut alice, "third");
+
+        assert_eq!(open(&mut bob, &third).unwrap(), "third");
+        assert_eq!(open(&mut bob, &first).unwrap(), "first");
+        assert_eq!(open(&mut bob, &second).unwrap(), "second");
+        assert!(bob.skipped.is_empty());
+    }
+
+    #[test]
+    fn replayed_message_is_rejected() {
+        let (mut alice, mut bob) = pair();
+        let msg = seal(&mut alice, "once");
+        assert_eq!(open(&mut bob, &msg).unwrap(), "once");
+        assert!(matches!(open(&mut bob, &msg), Err(OpenError::Replay(0))));
+    }
+
+    #[test]
+    fn gap_over_max_skip_is_rejected_without_state_change() {
+        let (mut alice, mut bob) = pair();
+        let first = seal(&mut alice, "first");
+        for _ in 0..MAX_SKIP {
+            alice.seal(b"lost").unwrap();
+        }
+        let far = seal(&mut alice, "far");
+
+        assert!(matches!(open(&mut bob, &far), Err(OpenError::SkipLimit(_))));
+        assert!(bob.skipped.is_empty());
+        assert_eq!(open(&mut bob, &first).unwrap(), "first");
+    }
+
+    #[test]
+    fn forged_frame_does_not_consume_keys() {
+        let (mut alice, mut bob) = pair();
+        let first = seal(&mut alice, "first");
+        let second = seal(&mut alice, "second");
+        assert_eq!(open(&mut bob, &second).unwrap(), "second");
+        assert_eq!(bob.skipped.len(), 1);
+
+        // Заголовок настоящий, шифртекст испорчен: отложенный ключ остаётся
+        let mut forged = first.clone();
+        *forged.last_mut().unwrap() ^= 1;
+        assert!(open(&mut bob, &forged).is_err());
+        assert_eq!(bob.skipped.len(), 1);
+        assert_eq!(open(&mut bob, &first).unwrap(), "first");
+    }
+
+    #[test]
+    fn late_initial_chain_message_after_dh_step() {
+        let (mut alice, mut bob) = pair();
+        // Alice делает DH-шаг, как только получила сообщение Bob
+        let hello = seal(&mut bob, "hello");
+        let early = seal(&mut alice, "early");
+        assert_eq!(open(&mut alice, &hello).unwrap(), "hello");
+        let stepped = seal(&mut alice, "stepped");
+
+        // Bob получает сообщение новой цепочки раньше начального
+        assert_eq!(open(&mut bob, &stepped).unwrap(), "stepped");
+        assert_eq!(open(&mut bob, &early).unwrap(), "early");
+    }
+}
@@ -1,16 +1,14 @@
-use crate::logger::log;
-use crate::peer::state::{
-    APP, COLLECTING_CANDIDATES, LOCAL_CANDIDATES, PEER, PENDING_REMOTE_CANDIDATES,
-};
+use crate::error::SscError;
+use crate::logger::Secret;
+use crate::peer::session::Session;
 use crate::peer::types::{IceCandidate, ServerConfig};
 use crate::utils::add_ice_url_scheme;
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::command;
-use tauri::AppHandle;
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tokio::time::timeout;
+use tracing::{debug, info, warn};
 use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
 use webrtc::{
     api::APIBuilder,
@@ -18,13 +16,16 @@ use webrtc::{
     peer_connection::{configuration::RTCConfiguration, RTCPeerConnection},
 };
 
-/// Применяет ICE кандидат от удаленной стороны
-#[command]
-pub async fn add_ice_candidate(app: AppHandle, candidate: IceCandidate) -> bool {
-    *APP.lock().unwrap() = Some(app);
-    log(&format!("add_ice_candidate called: {:?}", candidate));
+/// Применяет ICE кандидат от удаленной стороны к сессии
+///
+/// Без peer connection кандидат откладывается, но возвращается `NoPeer`.
+pub async fn add_ice_candidate(session: &Session, candidate: IceCandidate) -> Result<(), SscError> {
+    debug!(
+        "add_ice_candidate called for {}: {:?}",
+        session.id, candidate
+    );
 
-    let pc = { PEER.lock().unwrap().as_ref().cloned() };
+    let pc = { session.peer.lock().unwrap().as_ref().cloned() };
 
     if let Some(pc) = pc {
         // Если remote description уже установлен, применяем кандидат сразу
@@ -36,38 +37,41 @@ pub async fn add_ice_candidate(app: AppHandle, candidate: IceCandidate) -> bool
                 username_fragment: None,
             };
 
-            match pc.add_ice_candidate(ice_candidate).await {
-                Ok(_) => {
-                    log("Successfully added ICE candidate");
-                    true
-                }
-                Err(e) => {
-                    log(&format!("Failed to add ICE candidate: {:?}", e));
-                    false
-                }
-            }
+            pc.add_ice_candidate(ice_candidate)
+                .await
+                .inspect_err(|e| warn!("Failed to add ICE candidate: {:?}", e))?;
+            debug!("Successfully added ICE candidate");
+            Ok(())
         } else {
             // Если remote description еще не установлен, сохраняем кандидат
-            log("Remote description not set yet, queuing candidate");
-            PENDING_REMOTE_CANDIDATES.lock().unwrap().push(candidate);
-            true
+            debug!("Remote description not set yet, queuing candidate");
+            session
+                .pending_remote_candidates
+                .lock()
+                .unwrap()
+                .push(candidate);
+            Ok(())
         }
     } else {
-        log("No peer connection available, queuing candidate");
-        PENDING_REMOTE_CANDIDATES.lock().unwrap().push(candidate);
-        false
+        warn!("No peer connection available, queuing candidate");
+        session
+            .pending_remote_candidates
+            .lock()
+            .unwrap()
+            .push(candidate);
+        Err(SscError::NoPeer)
     }
 }
 
-/// Применяет все отложенные кандидаты после установки remote description
-pub async fn apply_pending_candidates(pc: &RTCPeerConnection) {
+/// Применяет все отложенные кандидаты сессии после установки remote description
+pub async fn apply_pending_candidates(session: &Session, pc: &RTCPeerConnection) {
     let candidates = {
-        let mut pending = PENDING_REMOTE_CANDIDATES.lock().unwrap();
+        let mut pending = session.pending_remote_candidates.lock().unwrap();
         pending.drain(..).collect::<Vec<_>>()
     };
 
     for candidate in candidates {
-        log(&format!("Applying pending candidate: {:?}", candidate));
+        debug!("Applying pending candidate: {:?}", candidate);
         let ice_candidate = RTCIceCandidateInit {
             candidate: candidate.candidate,
             sdp_mid: candidate.sdp_mid,
@@ -76,21 +80,21 @@ pub async fn apply_pending_candidates(pc: &RTCPeerConnection) {
         };
 
         if let Err(e) = pc.add_ice_candidate(ice_candidate).await {
-            log(&format!("Failed to apply pending candidate: {:?}", e));
+            warn!("Failed to apply pending candidate: {:?}", e);
         }
     }
 }
 
-#[command]
-pub async fn check_ice_server_availability(config: ServerConfig) -> bool {
-    log(&format!(
+/// Проверка STUN/TURN сервера: `true`, если получен кандидат нужного типа
+pub async fn check_ice_server_availability(config: ServerConfig) -> Result<bool, SscError> {
+    debug!(
         "check_ice_server_availability called with config: {:?}",
         config
-    ));
+    );
 
     let url = add_ice_url_scheme(&config);
 
-    log(&format!("Processed URL: '{}' -> '{}'", config.url, url));
+    debug!("Processed URL: '{}' -> '{}'", config.url, url);
 
     // Создаем ICE сервер из конфигурации
     let ice_server = RTCIceServer {
@@ -99,10 +103,12 @@ pub async fn check_ice_server_availability(config: ServerConfig) -> bool {
         credential: config.credential.clone().unwrap_or_default(),
     };
 
-    log(&format!(
-        "Created ICE server: urls={:?}, username='{}', credential='{}'",
-        ice_server.urls, ice_server.username, ice_server.credential
-    ));
+    debug!(
+        "Created ICE server: urls={:?}, username={}, credential={}",
+        ice_server.urls,
+        Secret(&ice_server.username),
+        Secret(&ice_server.credential)
+    );
 
     // Создаем конфигурацию для peer connection
     let rtc_config = RTCConfiguration {
@@ -110,36 +116,35 @@ pub async fn check_ice_server_availability(config: ServerConfig) -> bool {
         ..Default::default()
     };
 
-    log(&format!(
+    info!(
         "Created RTC config with {} ICE servers",
         rtc_config.ice_servers.len()
-    ));
+    );
 
     // Создаем API и peer connection
     let api = APIBuilder::new().build();
-    log("APIBuilder created successfully");
-
-    match api.new_peer_connection(rtc_config).await {
-        Ok(peer_connection) => {
-            log("Peer connection created successfully, starting ICE gathering check");
-            // Проверяем доступность через gathering состояние
-            check_via_ice_gathering(peer_connection.into(), &config.r#type).await
-        }
-        Err(e) => {
-            log(&format!("Failed to create peer connection: {:?}", e));
-            false
-        }
-    }
+    info!("APIBuilder created successfully");
+
+    let peer_connection = Arc::new(
+        api.new_peer_connection(rtc_config)
+            .await
+            .inspect_err(|e| warn!("Failed to create peer connection: {:?}", e))?,
+    );
+    info!("Peer connection created successfully, starting ICE gathering check");
+    // Проверяем доступность через gathering состояние
+    let result = check_via_ice_gathering(peer_connection.clone(), &config.r#type).await;
+    let _ = peer_connection.close().await;
+    result
 }
 
 async fn check_via_ice_gathering(
     peer_connection: Arc<RTCPeerConnection>,
     server_type: &str,
-) -> bool {
-    log(&format!(
+) -> Result<bool, SscError> {
+    debug!(
         "check_via_ice_gathering called with server_type: {}",
         server_type
-    ));
+    );
 
     let (tx, mut rx) = mpsc::channel(10);
     let tx_clone = tx.clone();
@@ -147,7 +152,7 @@ async fn check_via_ice_gathering(
     // Подписываемся на изменения состояния gathering
     peer_connection.on_ice_gathering_state_change(Box::new(move |state| {
         let tx = tx_clone.clone();
-        log(&format!("ICE gathering state changed to: {:?}", state));
+        debug!("ICE gathering state changed to: {:?}", state);
         tokio::spawn(async move {
             let _ = tx.send(state).await;
         });
@@ -164,124 +169,111 @@ async fn check_via_ice_gathering(
 
         Box::pin(async move {
             if let Some(c) = candidate {
-                log(&format!("Received ICE candidate: {:?}", c));
+                debug!("Received ICE candidate: {:?}", c);
                 // Проверяем тип кандидата
                 let candidate_type = c
                     .to_json()
                     .map(|json| {
-                        log(&format!("Candidate JSON: candidate='{}'", json.candidate));
+                        debug!("Candidate JSON: candidate='{}'", json.candidate);
                         // Для STUN серверов ищем srflx кандидатов
                         // Для TURN серверов ищем relay кандидатов
                         if server_type == "stun" && json.candidate.contains("srflx") {
-                            log("Found srflx candidate for STUN server");
+                            debug!("Found srflx candidate for STUN server");
                             true
                         } else if server_type == "turn" && json.candidate.contains("relay") {
-                            log("Found relay candidate for TURN server");
+                            debug!("Found relay candidate for TURN server");
                             true
                         } else {
-                            log(&format!(
+                            warn!(
                                 "Candidate type mismatch: expected {} but got candidate: {}",
                                 server_type, json.candidate
-                            ));
+                            );
                             false
                         }
                     })
                     .unwrap_or_else(|e| {
-                        log(&format!("Failed to get candidate JSON: {:?}", e));
+                        warn!("Failed to get candidate JSON: {:?}", e);
                         false
                     });
 
                 if candidate_type {
-                    log("Sending success signal for candidate match");
+                    debug!("Sending success signal for candidate match");
                     let _ = tx.send(true).await;
                 }
             } else {
-                log("Received null candidate (gathering complete)");
+                debug!("Received null candidate (gathering complete)");
             }
         })
     }));
 
     // Создаем data channel для инициации ICE gathering
-    log("Creating data channel to initiate ICE gathering");
-    match peer_connection.create_data_channel("test", None).await {
-        Ok(_) => {
-            log("Data channel created successfully");
-        }
-        Err(e) => {
-            log(&format!("Failed to create data channel: {:?}", e));
-            return false;
-        }
-    }
+    debug!("Creating data channel to initiate ICE gathering");
+    peer_connection
+        .create_data_channel("test", None)
+        .await
+        .inspect_err(|e| warn!("Failed to create data channel: {:?}", e))?;
+    info!("Data channel created successfully");
 
     // Создаем offer для запуска ICE gathering
-    log("Creating offer to start ICE gathering");
-    match peer_connection.create_offer(None).await {
-        Ok(offer) => {
-            log("Offer created successfully, setting local description");
-            if let Err(e) = peer_connection.set_local_description(offer).await {
-                log(&format!("Failed to set local description: {:?}", e));
-                return false;
-            }
-            log("Local description set successfully");
-        }
-        Err(e) => {
-            log(&format!("Failed to create offer: {:?}", e));
-            return false;
-        }
-    }
+    debug!("Creating offer to start ICE gathering");
+    let offer = peer_connection
+        .create_offer(None)
+        .await
+        .inspect_err(|e| warn!("Failed to create offer: {:?}", e))?;
+    info!("Offer created successfully, setting local description");
+    peer_connection
+        .set_local_description(offer)
+        .await
+        .inspect_err(|e| warn!("Failed to set local description: {:?}", e))?;
+    info!("Local description set successfully");
 
     // Ждем результат с таймаутом
     let check_timeout = Duration::from_secs(10);
-    log(&format!(
+    debug!(
         "Starting timeout wait of {} seconds",
         check_timeout.as_secs()
-    ));
+    );
 
     tokio::select! {
         // Ждем подходящего кандидата
         result = timeout(check_timeout, candidate_rx.recv()) => {
             match result {
-                Ok(Some(true)) => {
-                    log("Received success signal from candidate match");
-                    let _ = peer_connection.close().await;
-                    true
-                },
-                Ok(Some(false)) => {
-                    log("Received false signal from candidate match");
-                    let _ = peer_connection.close().await;
-                    false
+                Ok(Some(matched)) => {
+                    debug!("Received {} signal from candidate match", matched);
+                    Ok(matched)
                 },
                 Ok(None) => {
-                    log("Candidate channel closed without success signal");
-                    let _ = peer_connection.close().await;
-                    false
+                    info!("Candidate channel closed without success signal");
+                    Ok(false)
                 },
                 Err(_) => {
-                    log("Timeout waiting for candidate match");
-                    let _ = peer_connection.close().await;
-                    false
+                    debug!("Timeout waiting for candidate match");
+                    Err(SscError::Timeout(format!(
+                        "no {} candidate within {} s",
+                        server_type,
+                        check_timeout.as_secs()
+                    )))
                 }
             }
         }
         // Или ждем failed состояния
         _ = async {
             while let Some(state) = rx.recv().await {
-                log(&format!("Received gathering state: {:?}", state));
+                debug!("Received gathering state: {:?}", state);
                 if state == RTCIceGathererState::Complete {
-                    log("ICE gathering completed");
+                    info!("ICE gathering completed");
                     break;
                 }
             }
         } => {
-            log("Gathering state monitoring completed");
-            let _ = peer_connection.close().await;
-            false
+            info!("Gathering state monitoring completed");
+            Ok(false)
         }
     }
 }
 
 // Добавляем новую функцию для ожидания кандидатов с таймаутом
-pub async fn wait_for_candidates(timeout_secs: u64) -> Vec<IceCandidate> {
+pub async fn wait_for_candidates(session: &Session, timeout_secs: u64) -> Vec<IceCandidate> {
     let start = std::time::Instant::now();
 
     loop {
@@ -292,15 +284,15 @@ pub async fn wait_for_candidates(timeout_secs: u64) -> Vec<IceCandidate> {
         }
 
         // После 2 секунд проверяем состояние
-        let collecting = *COLLECTING_CANDIDATES.lock().unwrap();
-        let candidates_count = LOCAL_CANDIDATES.lock().unwrap().len();
+        let collecting = *session.collecting_candidates.lock().unwrap();
+        let candidates_count = session.local_candidates.lock().unwrap().len();
 
-        log(&format!(
+        info!(
             "Candidate collection status: collecting={}, count={}, elapsed={}s",
             collecting,
             candidates_count,
             start.elapsed().as_secs()
-        ));
+        );
 
         // Если сбор закончен ИЛИ есть хотя бы relay кандидаты - возвращаем
         if !collecting || candidates_count > 0 {
@@ -309,17 +301,17 @@ pub async fn wait_for_candidates(timeout_secs: u64) -> Vec<IceCandidate> {
 
         // Проверяем таймаут
         if start.elapsed().as_secs() >= timeout_secs {
-            log(&format!(
+            warn!(
                 "Candidate collection timeout after {} seconds",
                 timeout_secs
-            ));
+            );
             break;
         }
 
         sleep(Duration::from_millis(100)).await;
     }
 
-    LOCAL_CANDIDATES.lock().unwrap().clone()
+    session.local_candidates.lock().unwrap().clone()
 }
 
 pub fn analyze_candidates(candidates: &[IceCandidate]) {
@@ -337,12 +329,12 @@ pub fn analyze_candidates(candidates: &[IceCandidate]) {
         }
     }
 
-    log(&format!(
+    info!(
         "Candidate analysis: {} host, {} srflx, {} relay",
         host_count, srflx_count, relay_count
-    ));
+    );
 
     if relay_count == 0 {
-        log("WARNING: No TURN relay candidates found! Connection through NAT may fail.");
+        warn!("No TURN relay candidates found! Connection through NAT may fail.");
     }
 }
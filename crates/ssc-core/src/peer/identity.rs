@@ -0,0 +1,279 @@
+use crate::peer::state::{DATA_DIR, LOCAL_IDENTITY};
+use base64::{engine::general_purpose, Engine as _};
+use rand::Rng;
+use ring::rand as ring_rand;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tracing::info;
+
+const IDENTITY_FILE: &str = "identity.json";
+const PINNED_FILE: &str = "pinned_contacts.json";
+
+/// Метка подписи: подпись ключа идентичности нельзя переиспользовать в другом протоколе
+const SIGNATURE_LABEL: &[u8] = b"ssc-identity-v1";
+
+/// Долговременный ключ установки
+pub struct Identity {
+    pub installation_id: String,
+    keypair: Ed25519KeyPair,
+}
+
+/// Как ключ хранится на диске
+#[derive(Serialize, Deserialize)]
+struct StoredIdentity {
+    installation_id: String,
+    pkcs8: String, // base64
+}
+
+/// Подпись эфемерного ключа, передаётся вместе с ним в кадре Handshake
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdentityClaim {
+    pub installation_id: String,
+    pub identity_key: String, // hex Ed25519 public key
+    pub signature: String,    // hex подписи над меткой, installation_id и X25519-ключом
+}
+
+/// Проверенная идентичность собеседника
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub installation_id: String,
+    pub identity_key: [u8; 32],
+}
+
+impl PeerIdentity {
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.identity_key)
+    }
+}
+
+/// Запись хранилища закреплённых контактов
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PinnedContact {
+    pub fingerprint: String,
+    pub installation_id: String,
+    pub identity_key: String,
+    pub first_seen: i64,
+    pub verified: bool, // SAS подтверждён хотя бы в одной сессии с этим ключом
+}
+
+/// Результат сверки с закреплёнными контактами
+pub enum PinStatus {
+    /// Ключ встречается впервые; закрепляется только после сверки SAS
+    New,
+    /// Ключ уже закреплён
+    Known { verified: bool },
+    /// Та же установка пришла с другим ключом идентичности
+    Changed { previous: PinnedContact },
+}
+
+/// Что известно о ключе идентичности собеседника в сессии (для UI)
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityState {
+    /// Ключ не подтверждался по SAS: собеседник не проверен
+    Unknown,
+    /// Ключ закреплён после сверки SAS
+    Verified,
+    /// Установка известна, но пришла с другим ключом
+    Changed,
+}
+
+/// Payload события `ssc-identity-changed`
+#[derive(Serialize, Debug, Clone)]
+pub struct IdentityChanged {
+    pub installation_id: String,
+    pub previous_fingerprint: String,
+    pub new_fingerprint: String,
+}
+
+/// Отпечаток ключа идентичности: первые 16 байт SHA-256
+pub fn fingerprint(identity_key: &[u8]) -> String {
+    hex::encode(&Sha256::digest(identity_key)[..16])
+}
+
+fn data_dir() -> Option<PathBuf> {
+    let dir = DATA_DIR.lock().unwrap().clone()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+/// Загружаем ключ идентичности с диска или создаём новый
+fn load_or_create() -> Option<Identity> {
+    let path = data_dir()?.join(IDENTITY_FILE);
+
+    if let Ok(raw) = std::fs::read(&path) {
+        let stored: StoredIdentity = serde_json::from_slice(&raw).ok()?;
+        let pkcs8 = general_purpose::STANDARD.decode(stored.pkcs8).ok()?;
+        let keypair = Ed25519KeyPair::from_pkcs8(&pkcs8).ok()?;
+        return Some(Identity {
+            installation_id: stored.installation_id,
+            keypair,
+        });
+    }
+
+    let rng = ring_rand::SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).ok()?;
+    let keypair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).ok()?;
+    let installation_id = hex::encode(rand::rng().random::<[u8; 16]>());
+
+    let stored = StoredIdentity {
+        installation_id: installation_id.clone(),
+        pkcs8: general_purpose::STANDARD.encode(pkcs8.as_ref()),
+    };
+    std::fs::write(&path, serde_json::to_vec(&stored).ok()?).ok()?;
+    info!(
+        "Created identity key {}",
+        fingerprint(keypair.public_key().as_ref())
+    );
+
+    Some(Identity {
+        installation_id,
+        keypair,
+    })
+}
+
+/// Гарантирует, что ключ идентичности загружен; false, если хранилище недоступно
+pub fn ensure_identity() -> bool {
+    let mut identity = LOCAL_IDENTITY.lock().unwrap();
+    if identity.is_none() {
+        *identity = load_or_create();
+    }
+    identity.is_some()
+}
+
+/// Отпечаток нашего ключа идентичности
+pub fn local_fingerprint() -> Option<String> {
+    ensure_identity();
+    LOCAL_IDENTITY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|id| fingerprint(id.keypair.public_key().as_ref()))
+}
+
+fn signed_message(installation_id: &str, ephemeral: &[u8; 32]) -> Vec<u8> {
+    let mut msg = SIGNATURE_LABEL.to_vec();
+    msg.extend_from_slice(&(installation_id.len() as u32).to_be_bytes());
+    msg.extend_from_slice(installation_id.as_bytes());
+    msg.extend_from_slice(ephemeral);
+    msg
+}
+
+/// Подпись ключом идентичности: `message` строит подписываемые байты по installation_id
+///
+/// Возвращает installation_id, публичный ключ и подпись.
+pub fn sign_with_identity(
+    message: impl FnOnce(&str) -> Vec<u8>,
+) -> Option<(String, [u8; 32], Vec<u8>)> {
+    ensure_identity();
+    let identity = LOCAL_IDENTITY.lock().unwrap();
+    let identity = identity.as_ref()?;
+    let sig = identity.keypair.sign(&message(&identity.installation_id));
+    let public = <[u8; 32]>::try_from(identity.keypair.public_key().as_ref()).ok()?;
+
+    Some((
+        identity.installation_id.clone(),
+        public,
+        sig.as_ref().to_vec(),
+    ))
+}
+
+/// Подписываем эфемерный X25519-ключ ключом идентичности
+pub fn sign_ephemeral(ephemeral: &[u8; 32]) -> Option<IdentityClaim> {
+    let (installation_id, public, sig) = sign_with_identity(|id| signed_message(id, ephemeral))?;
+
+    Some(IdentityClaim {
+        installation_id,
+        identity_key: hex::encode(public),
+        signature: hex::encode(sig),
+    })
+}
+
+/// Проверяем подпись собеседника над его эфемерным ключом
+pub fn verify_claim(claim: &IdentityClaim, ephemeral: &[u8; 32]) -> Option<PeerIdentity> {
+    let identity_key = <[u8; 32]>::try_from(hex::decode(&claim.identity_key).ok()?).ok()?;
+    let sig = hex::decode(&claim.signature).ok()?;
+
+    signature::UnparsedPublicKey::new(&signature::ED25519, identity_key)
+        .verify(&signed_message(&claim.installation_id, ephemeral), &sig)
+        .ok()?;
+
+    Some(PeerIdentity {
+        installation_id: claim.installation_id.clone(),
+        identity_key,
+    })
+}
+
+/// Все закреплённые контакты
+pub fn load_pinned() -> Vec<PinnedContact> {
+    data_dir()
+        .and_then(|dir| std::fs::read(dir.join(PINNED_FILE)).ok())
+        .and_then(|raw| serde_json::from_slice(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_pinned(contacts: &[PinnedContact]) -> bool {
+    let Some(dir) = data_dir() else {
+        return false;
+    };
+    match serde_json::to_vec_pretty(contacts) {
+        Ok(raw) => std::fs::write(dir.join(PINNED_FILE), raw).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Сверяем идентичность собеседника с хранилищем, ничего не записывая
+///
+/// Неизвестный ключ не закрепляется: installation_id и ключ сообщает сам
+/// собеседник, поэтому доверять ключу можно только после сверки SAS (`pin_verified`).
+pub fn check_pinned(peer: &PeerIdentity) -> PinStatus {
+    let contacts = load_pinned();
+    let fp = peer.fingerprint();
+
+    if let Some(known) = contacts.iter().find(|c| c.fingerprint == fp) {
+        return PinStatus::Known {
+            verified: known.verified,
+        };
+    }
+    if let Some(previous) = contacts
+        .iter()
+        .find(|c| c.installation_id == peer.installation_id)
+    {
+        // Старую запись не трогаем: заменить её может только подтверждение SAS
+        return PinStatus::Changed {
+            previous: previous.clone(),
+        };
+    }
+    PinStatus::New
+}
+
+/// Закрепляем ключ как подтверждённый, вытесняя прежний ключ той же установки
+pub fn pin_verified(peer: &PeerIdentity) -> bool {
+    let fp = peer.fingerprint();
+    let mut contacts = load_pinned();
+    let first_seen = contacts
+        .iter()
+        .find(|c| c.fingerprint == fp)
+        .map(|c| c.first_seen)
+        .unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+    contacts.retain(|c| c.fingerprint != fp && c.installation_id != peer.installation_id);
+    contacts.push(PinnedContact {
+        fingerprint: fp,
+        installation_id: peer.installation_id.clone(),
+        identity_key: hex::encode(peer.identity_key),
+        first_seen,
+        verified: true,
+    });
+    save_pinned(&contacts)
+}
+
+/// Удаляем контакт из хранилища
+pub fn forget(fingerprint: &str) -> bool {
+    let mut contacts = load_pinned();
+    let before = contacts.len();
+    contacts.retain(|c| c.fingerprint != fingerprint);
+    contacts.len() != before && save_pinned(&contacts)
+}
@@ -0,0 +1,80 @@
+use crate::peer::crypto::ZeroizedKey;
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, EncodedSizeUser, KemCore, MlKem768};
+use rand_core::OsRng;
+
+/// Закрытый ключ ML-KEM-768 текущего обмена
+pub type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Длина ключа инкапсуляции ML-KEM-768
+pub const ENCAPSULATION_KEY_LEN: usize = 1184;
+
+/// Длина шифротекста ML-KEM-768
+pub const CIPHERTEXT_LEN: usize = 1088;
+
+/// Гибридный обмен, ожидающий шифротекста собеседника
+pub struct PendingKem {
+    pub peer_pub: [u8; 32],
+    /// Секрет, инкапсулированный нами к ключу собеседника
+    pub outgoing: ZeroizedKey,
+}
+
+/// Новая пара ML-KEM-768: закрытый ключ и закодированный ключ инкапсуляции
+pub fn generate() -> (DecapsulationKey, Vec<u8>) {
+    let (dk, ek) = MlKem768::generate(&mut OsRng);
+    (dk, ek.as_bytes().to_vec())
+}
+
+/// Инкапсуляция к ключу собеседника: шифротекст для него и общий секрет
+pub fn encapsulate(encapsulation_key: &[u8]) -> Option<(Vec<u8>, ZeroizedKey)> {
+    if encapsulation_key.len() != ENCAPSULATION_KEY_LEN {
+        return None;
+    }
+    let ek = EncapsulationKey::from_bytes(encapsulation_key.try_into().ok()?);
+    let (ct, shared) = ek.encapsulate(&mut OsRng).ok()?;
+    Some((ct.to_vec(), ZeroizedKey::new(shared.into())))
+}
+
+/// Извлекаем общий секрет из шифротекста собеседника
+pub fn decapsulate(dk: &DecapsulationKey, ciphertext: &[u8]) -> Option<ZeroizedKey> {
+    if ciphertext.len() != CIPHERTEXT_LEN {
+        return None;
+    }
+    let ct = Ciphertext::<MlKem768>::try_from(ciphertext).ok()?;
+    let shared = dk.decapsulate(&ct).ok()?;
+    Some(ZeroizedKey::new(shared.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encapsulated_secret_matches_decapsulated() {
+        let (dk, ek) = generate();
+        assert_eq!(ek.len(), ENCAPSULATION_KEY_LEN);
+        let (ciphertext, outgoing) = encapsulate(&ek).unwrap();
+        assert_eq!(ciphertext.len(), CIPHERTEXT_LEN);
+        let incoming = decapsulate(&dk, &ciphertext).unwrap();
+        assert_eq!(outgoing.key, incoming.key);
+    }
+
+    #[test]
+    fn wrong_lengths_are_rejected() {
+        let (dk, ek) = generate();
+        assert!(encapsulate(&ek[1..]).is_none());
+        let (ciphertext, _) = encapsulate(&ek).unwrap();
+        assert!(decapsulate(&dk, &ciphertext[1..]).is_none());
+    }
+
+    #[test]
+    fn altered_ciphertext_gives_a_different_secret() {
+        let (dk, ek) = generate();
+        let (mut ciphertext, outgoing) = encapsulate(&ek).unwrap();
+        ciphertext[0] ^= 1;
+        // ML-KEM не сообщает об ошибке, а выдаёт другой секрет: ключи разойдутся
+        let incoming = decapsulate(&dk, &ciphertext).unwrap();
+        assert_ne!(outgoing.key, incoming.key);
+    }
+}
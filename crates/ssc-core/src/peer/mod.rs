@@ -0,0 +1,16 @@
+pub mod connection;
+pub mod crypto;
+pub mod data_channel;
+pub mod double_ratchet;
+pub mod ice;
+pub mod identity;
+pub mod kem;
+pub mod negotiation;
+pub mod pake;
+pub mod ratchet;
+pub mod sas;
+pub mod session;
+pub mod signaling;
+pub mod state;
+pub mod traffic;
+pub mod types;
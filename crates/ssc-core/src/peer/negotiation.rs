@@ -0,0 +1,163 @@
+use crate::peer::state::{DOUBLE_RATCHET_ENABLED, PQ_HYBRID_ENABLED};
+use serde::{Deserialize, Serialize};
+
+/// Версии протокола data channel/crypto, которые понимает эта сборка
+///
+/// 1 - сообщение шифруется как есть; 2 - внутренний кадр с типом, длиной и выравниванием.
+pub const SUPPORTED_VERSIONS: &[u8] = &[1, 2];
+
+/// Первая версия, в которой открытый текст упакован во внутренний кадр
+pub const PADDED_VERSION: u8 = 2;
+
+/// Набор криптографических алгоритмов сессии
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// X25519 + HKDF-SHA256 + ChaCha20-Poly1305, симметричный храповик
+    SymmetricRatchet,
+    /// X25519 + HKDF-SHA256 + ChaCha20-Poly1305, Double Ratchet
+    DoubleRatchet,
+}
+
+impl CipherSuite {
+    /// Глобальный порядок предпочтения: одинаков у обеих сторон, поэтому выбор детерминирован
+    const PREFERENCE: &'static [CipherSuite] =
+        &[CipherSuite::DoubleRatchet, CipherSuite::SymmetricRatchet];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SymmetricRatchet => "x25519-chacha20poly1305-ratchet",
+            Self::DoubleRatchet => "x25519-chacha20poly1305-double-ratchet",
+        }
+    }
+}
+
+/// Алгоритм обмена ключами
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExchange {
+    /// Только X25519
+    X25519,
+    /// X25519 и ML-KEM-768: оба общих секрета идут в HKDF
+    X25519MlKem768,
+}
+
+impl KeyExchange {
+    /// Порядок предпочтения, как и для наборов алгоритмов
+    const PREFERENCE: &'static [KeyExchange] = &[KeyExchange::X25519MlKem768];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::X25519 => "x25519",
+            Self::X25519MlKem768 => "x25519-mlkem768",
+        }
+    }
+
+    /// Нужен ли обмен ML-KEM поверх X25519
+    pub fn is_hybrid(&self) -> bool {
+        *self == Self::X25519MlKem768
+    }
+}
+
+/// Первое сообщение по data channel: что умеет эта сторона
+///
+/// Кадр Hello всегда передаётся в конверте версии 1, чтобы любые две сборки
+/// могли его разобрать. Неизвестные наборы просто игнорируются.
+/// `key_exchanges` перечисляет дополнительные к X25519 обмены; старые сборки
+/// поле не знают и не передают, и тогда остаётся только X25519.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hello {
+    pub versions: Vec<u8>,
+    pub suites: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_exchanges: Vec<String>,
+}
+
+/// Результат согласования
+#[derive(Debug, Clone, Copy)]
+pub struct Negotiated {
+    pub version: u8,
+    pub suite: CipherSuite,
+    pub key_exchange: KeyExchange,
+}
+
+/// Payload события `ssc-incompatible-peer`
+///
+/// Пустые `remote_versions` - собеседник старой версии без обмена Hello.
+#[derive(Serialize, Debug, Clone)]
+pub struct IncompatiblePeer {
+    pub local_versions: Vec<u8>,
+    pub remote_versions: Vec<u8>,
+    pub local_suites: Vec<String>,
+    pub remote_suites: Vec<String>,
+}
+
+impl IncompatiblePeer {
+    /// Собеседник старой версии: шлёт кадры без конверта и не знает о Hello
+    pub fn legacy(local: &Hello) -> Self {
+        Self {
+            local_versions: local.versions.clone(),
+            remote_versions: Vec::new(),
+            local_suites: local.suites.clone(),
+            remote_suites: Vec::new(),
+        }
+    }
+}
+
+/// Наш Hello с учётом текущих настроек; сессия берёт его при создании
+pub fn local_hello() -> Hello {
+    let mut suites = Vec::new();
+    if *DOUBLE_RATCHET_ENABLED.lock().unwrap() {
+        suites.push(CipherSuite::DoubleRatchet.as_str().to_string());
+    }
+    suites.push(CipherSuite::SymmetricRatchet.as_str().to_string());
+
+    let mut key_exchanges = Vec::new();
+    if *PQ_HYBRID_ENABLED.lock().unwrap() {
+        key_exchanges.push(KeyExchange::X25519MlKem768.as_str().to_string());
+    }
+
+    Hello {
+        versions: SUPPORTED_VERSIONS.to_vec(),
+        suites,
+        key_exchanges,
+    }
+}
+
+/// Выбираем наибольшую общую версию, лучший общий набор алгоритмов и обмен ключами
+///
+/// Гибридный обмен не обязателен: если его не предложили обе стороны, остаётся X25519.
+pub fn negotiate(local: &Hello, remote: &Hello) -> Result<Negotiated, IncompatiblePeer> {
+    let version = local
+        .versions
+        .iter()
+        .filter(|v| remote.versions.contains(v))
+        .max()
+        .copied();
+
+    let suite = CipherSuite::PREFERENCE.iter().copied().find(|c| {
+        let name = c.as_str().to_string();
+        local.suites.contains(&name) && remote.suites.contains(&name)
+    });
+
+    let key_exchange = KeyExchange::PREFERENCE
+        .iter()
+        .copied()
+        .find(|k| {
+            let name = k.as_str().to_string();
+            local.key_exchanges.contains(&name) && remote.key_exchanges.contains(&name)
+        })
+        .unwrap_or(KeyExchange::X25519);
+
+    match (version, suite) {
+        (Some(version), Some(suite)) => Ok(Negotiated {
+            version,
+            suite,
+            key_exchange,
+        }),
+        _ => Err(IncompatiblePeer {
+            local_versions: local.versions.clone(),
+            remote_versions: remote.versions.clone(),
+            local_suites: local.suites.clone(),
+            remote_suites: remote.suites.clone(),
+        }),
+    }
+}
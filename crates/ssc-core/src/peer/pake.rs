@@ -0,0 +1,115 @@
+use crate::peer::crypto::ZeroizedKey;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use rand::Rng;
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Роль в обмене CPace: инициатор кладёт своё сообщение в offer, ответчик - в answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PakeRole {
+    Initiator,
+    Responder,
+}
+
+/// Незавершённый обмен CPace (ristretto255, SHA-512)
+///
+/// Генератор выводится из пароля и идентификатора соединения, поэтому перехвативший
+/// offer не может ни ответить на него, ни перебирать пароль офлайн по сообщениям.
+pub struct Pake {
+    role: PakeRole,
+    sid: String,
+    secret: Scalar,
+    message: [u8; 32],
+}
+
+impl Drop for Pake {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+fn prefixed(hasher: &mut Sha512, data: &[u8]) {
+    hasher.update((data.len() as u32).to_be_bytes());
+    hasher.update(data);
+}
+
+/// Пустой пароль означает обычный режим без PAKE
+pub fn normalize_passphrase(passphrase: Option<String>) -> Option<String> {
+    passphrase
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+}
+
+/// Разбор сообщения CPace из bundle (hex, 32 байта)
+pub fn parse_message(encoded: &str) -> Option<[u8; 32]> {
+    <[u8; 32]>::try_from(hex::decode(encoded).ok()?).ok()
+}
+
+/// Генератор группы из пароля и sid (hash-to-group через from_uniform_bytes)
+fn generator(passphrase: &str, sid: &str) -> RistrettoPoint {
+    let mut hasher = Sha512::new();
+    prefixed(&mut hasher, b"CPaceRistretto255");
+    prefixed(&mut hasher, passphrase.as_bytes());
+    prefixed(&mut hasher, sid.as_bytes());
+    let mut digest = hasher.finalize();
+    let mut uniform = Zeroizing::new([0u8; 64]);
+    uniform.copy_from_slice(&digest);
+    digest.as_mut_slice().zeroize();
+    RistrettoPoint::from_uniform_bytes(&uniform)
+}
+
+impl Pake {
+    /// Начинаем обмен: секретный скаляр и наше сообщение `y * G`
+    pub fn start(role: PakeRole, passphrase: &str, sid: &str) -> Self {
+        let wide = Zeroizing::new(rand::rng().random::<[u8; 64]>());
+        let secret = Scalar::from_bytes_mod_order_wide(&wide);
+        let message = (secret * generator(passphrase, sid)).compress().to_bytes();
+
+        Self {
+            role,
+            sid: sid.to_string(),
+            secret,
+            message,
+        }
+    }
+
+    /// Наше сообщение для передачи в bundle
+    pub fn message(&self) -> [u8; 32] {
+        self.message
+    }
+
+    /// Завершаем обмен по сообщению собеседника
+    ///
+    /// При разных паролях обе стороны получат разные ключи; это обнаружится
+    /// на подтверждении ключей по data channel.
+    pub fn finish(self, peer_message: &[u8; 32]) -> Option<ZeroizedKey> {
+        let peer = CompressedRistretto(*peer_message).decompress()?;
+        if peer.is_identity() {
+            return None;
+        }
+        let shared = self.secret * peer;
+        if shared.is_identity() {
+            return None;
+        }
+
+        let (first, second) = match self.role {
+            PakeRole::Initiator => (&self.message, peer_message),
+            PakeRole::Responder => (peer_message, &self.message),
+        };
+
+        let mut hasher = Sha512::new();
+        prefixed(&mut hasher, b"CPaceRistretto255_ISK");
+        prefixed(&mut hasher, self.sid.as_bytes());
+        prefixed(&mut hasher, shared.compress().as_bytes());
+        prefixed(&mut hasher, first);
+        prefixed(&mut hasher, second);
+        let mut digest = hasher.finalize();
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&digest[..32]);
+        digest.as_mut_slice().zeroize();
+        Some(ZeroizedKey::new(key))
+    }
+}
@@ -0,0 +1,182 @@
+use crate::peer::crypto::{make_nonce, OpenError, ReplayWindow, ZeroizedKey};
+use crate::peer::state::{MAX_EPOCH_LAG, RATCHET_AFTER_MESSAGES, RATCHET_INTERVAL};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::time::Instant;
+use zeroize::Zeroize;
+
+/// Шаг HKDF-цепочки: из ключа цепочки получаем следующий ключ цепочки и ключ эпохи
+pub fn kdf_chain(chain_key: &ZeroizedKey) -> (ZeroizedKey, ZeroizedKey) {
+    let hk = Hkdf::<Sha256>::new(None, &chain_key.key);
+    let mut okm = [0u8; 64];
+    hk.expand(b"ssc-ratchet", &mut okm).unwrap();
+
+    let mut next_chain = [0u8; 32];
+    let mut epoch_key = [0u8; 32];
+    next_chain.copy_from_slice(&okm[..32]);
+    epoch_key.copy_from_slice(&okm[32..]);
+    okm.zeroize();
+
+    (ZeroizedKey::new(next_chain), ZeroizedKey::new(epoch_key))
+}
+
+/// Ключ одной эпохи храповика
+struct EpochKey {
+    epoch: u32,
+    cipher: ChaCha20Poly1305,
+    _key: ZeroizedKey,
+}
+
+impl EpochKey {
+    /// Выводит ключ эпохи `epoch` и возвращает его вместе с ключом цепочки для следующей
+    fn derive(epoch: u32, chain_key: &ZeroizedKey) -> (Self, ZeroizedKey) {
+        let (next_chain, key) = kdf_chain(chain_key);
+        let cipher = ChaCha20Poly1305::new(&Key::from(key.key));
+        (
+            Self {
+                epoch,
+                cipher,
+                _key: key,
+            },
+            next_chain,
+        )
+    }
+}
+
+/// Отправляющая цепочка: ключ сдвигается каждые N сообщений или M минут
+pub struct SendChain {
+    current: EpochKey,
+    chain_key: ZeroizedKey, // ключ цепочки для следующей эпохи
+    seq: u64,               // последний использованный номер в текущей эпохе
+    started: Instant,
+}
+
+impl SendChain {
+    pub fn new(root: ZeroizedKey) -> Self {
+        let (current, chain_key) = EpochKey::derive(0, &root);
+        Self {
+            current,
+            chain_key,
+            seq: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Сдвигает храповик, старый ключ эпохи зануляется при удалении
+    fn ratchet(&mut self) {
+        let (next, chain_key) = EpochKey::derive(self.current.epoch + 1, &self.chain_key);
+        self.current = next;
+        self.chain_key = chain_key;
+        self.seq = 0;
+        self.started = Instant::now();
+    }
+
+    /// Шифрует сообщение, при необходимости переходя к новой эпохе
+    pub fn seal(&mut self, plaintext: &[u8]) -> Option<(u32, u64, Vec<u8>)> {
+        if self.seq >= RATCHET_AFTER_MESSAGES || self.started.elapsed() >= RATCHET_INTERVAL {
+            self.ratchet();
+        }
+
+        self.seq += 1;
+        let epoch = self.current.epoch;
+        let seq = self.seq;
+        let ciphertext = self
+            .current
+            .cipher
+            .encrypt(&make_nonce(epoch, seq), plaintext)
+            .ok()?;
+        Some((epoch, seq, ciphertext))
+    }
+}
+
+/// Принятая эпоха вместе со своим окном replay
+struct RecvEpoch {
+    key: EpochKey,
+    window: ReplayWindow,
+}
+
+impl RecvEpoch {
+    fn open(&mut self, seq: u64, ciphertext: &[u8]) -> Result<Vec<u8>, OpenError> {
+        // Отбрасываем дубликаты до расшифровки
+        if !self.window.check(seq) {
+            return Err(OpenError::Replay(seq));
+        }
+
+        // Nonce выводится из (epoch, seq), поэтому подмена заголовка ломает аутентификацию
+        let plaintext = self
+            .key
+            .cipher
+            .decrypt(&make_nonce(self.key.epoch, seq), ciphertext)
+            .map_err(|_| OpenError::Decrypt(seq))?;
+
+        // Окно сдвигаем только после успешной аутентификации
+        self.window.accept(seq);
+        Ok(plaintext)
+    }
+}
+
+/// Принимающая цепочка: догоняет отправителя до `MAX_EPOCH_LAG` эпох вперёд
+/// и хранит предыдущую эпоху для сообщений, переупорядоченных на границе
+pub struct RecvChain {
+    current: RecvEpoch,
+    previous: Option<RecvEpoch>,
+    chain_key: ZeroizedKey,
+}
+
+impl RecvChain {
+    pub fn new(root: ZeroizedKey) -> Self {
+        let (key, chain_key) = EpochKey::derive(0, &root);
+        Self {
+            current: RecvEpoch {
+                key,
+                window: ReplayWindow::default(),
+            },
+            previous: None,
+            chain_key,
+        }
+    }
+
+    /// Текущая эпоха принимающей цепочки
+    pub fn epoch(&self) -> u32 {
+        self.current.key.epoch
+    }
+
+    pub fn open(&mut self, epoch: u32, seq: u64, ciphertext: &[u8]) -> Result<Vec<u8>, OpenError> {
+        let current_epoch = self.current.key.epoch;
+
+        if epoch == current_epoch {
+            return self.current.open(seq, ciphertext);
+        }
+
+        if epoch < current_epoch {
+            return match self.previous.as_mut() {
+                Some(prev) if prev.key.epoch == epoch => prev.open(seq, ciphertext),
+                _ => Err(OpenError::UnknownEpoch(epoch)),
+            };
+        }
+
+        if epoch - current_epoch > MAX_EPOCH_LAG {
+            return Err(OpenError::UnknownEpoch(epoch));
+        }
+
+        // Выводим ключи вперёд во временные переменные: состояние меняем
+        // только если сообщение прошло аутентификацию
+        let (mut key, mut chain_key) = EpochKey::derive(current_epoch + 1, &self.chain_key);
+        while key.epoch < epoch {
+            (key, chain_key) = EpochKey::derive(key.epoch + 1, &chain_key);
+        }
+        let mut candidate = RecvEpoch {
+            key,
+            window: ReplayWindow::default(),
+        };
+        let plaintext = candidate.open(seq, ciphertext)?;
+
+        self.previous = Some(std::mem::replace(&mut self.current, candidate));
+        self.chain_key = chain_key;
+        Ok(plaintext)
+    }
+}
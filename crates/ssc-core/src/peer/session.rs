@@ -0,0 +1,375 @@
+use crate::error::SscError;
+use crate::events::{emit_disconnected, Events};
+use crate::peer::crypto::{CryptoCtx, ZeroizedKey};
+use crate::peer::identity::{IdentityState, PeerIdentity};
+use crate::peer::kem::{DecapsulationKey, PendingKem};
+use crate::peer::negotiation::{local_hello, Hello, Negotiated};
+use crate::peer::pake::Pake;
+use crate::peer::types::IceCandidate;
+use ring::agreement;
+use ring::signature::Ed25519KeyPair;
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+use webrtc::{data_channel::RTCDataChannel, peer_connection::RTCPeerConnection};
+use zeroize::Zeroizing;
+
+/// Состояние разговора для списка сессий
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    /// Собираем ICE кандидаты для offer/answer
+    Gathering,
+    /// Ждём answer или установки соединения и обмена ключами
+    Connecting,
+    /// Ключи согласованы, SAS ещё не подтверждён
+    AwaitingVerification,
+    /// Ключи согласованы и SAS подтверждён
+    Connected,
+    /// Соединение потеряно, идёт grace period
+    Recovering,
+}
+
+/// Строка списка сессий
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub state: SessionState,
+    pub active: bool,
+    pub created_at: i64,
+    /// `None` - собеседник не подписал ключ идентичностью или ключи ещё не согласованы
+    pub identity: Option<IdentityState>,
+}
+
+/// Состояние одного разговора: соединение, data channel, ключи и SAS
+///
+/// Ключ сессии - `connection_id` из offer, он одинаков у обеих сторон.
+pub struct Session {
+    pub id: String,
+
+    /// Время создания сессии (unix-время, секунды)
+    pub created_at: i64,
+
+    /// Куда публикуются события сессии
+    pub events: Events,
+
+    /// Менеджер, из которого сессия удаляется при отключении
+    pub manager: Weak<SessionManager>,
+
+    /// WebRTC Peer Connection
+    pub peer: Mutex<Option<Arc<RTCPeerConnection>>>,
+
+    /// Data Channel для обмена сообщениями
+    pub data_ch: Mutex<Option<Arc<RTCDataChannel>>>,
+
+    /// Криптографический контекст для шифрования
+    pub crypto: Mutex<Option<CryptoCtx>>,
+
+    /// Контекст, ожидающий подтверждения ключей собеседником
+    pub pending_crypto: Mutex<Option<CryptoCtx>>,
+
+    /// Проверенная идентичность собеседника
+    pub remote_identity: Mutex<Option<PeerIdentity>>,
+
+    /// Идентичность, которой подписан пакет сигнализации собеседника
+    ///
+    /// Если она есть, ключ в Handshake обязан быть подписан ею же.
+    pub bundle_identity: Mutex<Option<PeerIdentity>>,
+
+    /// Ключ, которым мы подписали свой offer или answer
+    pub local_signer: Mutex<Option<[u8; 32]>>,
+
+    /// Ключ, которым подписан offer или answer собеседника
+    ///
+    /// Оба ключа подписи входят в транскрипт обмена ключами.
+    pub remote_signer: Mutex<Option<[u8; 32]>>,
+
+    /// Сверка идентичности собеседника с закреплёнными контактами
+    pub identity_state: Mutex<Option<IdentityState>>,
+
+    /// Сессионный ключ подписи пакетов сигнализации (если ключи идентичности выключены)
+    pub signaling_key: Mutex<Option<Ed25519KeyPair>>,
+
+    /// Обмен CPace, ожидающий answer (сторона offer)
+    pub pake_pending: Mutex<Option<Pake>>,
+
+    /// Ключ из CPace, подмешивается в вывод ключей сессии
+    pub pake_key: Mutex<Option<ZeroizedKey>>,
+
+    /// Код переноса offer, которым должен быть зашифрован answer
+    pub transport_code: Mutex<Option<String>>,
+
+    /// connection_id нашего offer, на который ещё не применён answer
+    pub pending_offer_id: Mutex<Option<String>>,
+
+    /// Что предлагает наш Hello: настройки на момент создания сессии
+    pub offered_hello: Hello,
+
+    /// Наш Hello (сериализованный, как отправлен) и Hello собеседника
+    pub local_hello: Mutex<Option<Vec<u8>>>,
+    pub remote_hello: Mutex<Option<Vec<u8>>>,
+
+    /// Согласованные версия протокола и набор алгоритмов
+    pub negotiated: Mutex<Option<Negotiated>>,
+
+    /// Ключ собеседника, пришедший раньше его Hello: обрабатывается после согласования
+    pub early_handshake: Mutex<Option<Vec<u8>>>,
+
+    /// Приватный ключ для обмена
+    pub my_priv: Mutex<Option<agreement::EphemeralPrivateKey>>,
+
+    /// Публичный ключ для обмена
+    pub my_pub: Mutex<Option<[u8; 32]>>,
+
+    /// Закрытый ключ ML-KEM текущего обмена (только в гибридном режиме)
+    pub my_kem: Mutex<Option<DecapsulationKey>>,
+
+    /// Гибридный обмен, ожидающий шифротекста ML-KEM от собеседника
+    pub pending_kem: Mutex<Option<PendingKem>>,
+
+    /// Номер последнего отправленного кадра data channel
+    pub send_frame_seq: Mutex<u64>,
+
+    /// Флаг установленного соединения
+    pub was_connected: Mutex<bool>,
+
+    /// Отложенная задача для graceful disconnect
+    pub disconnect_task: Mutex<Option<JoinHandle<()>>>,
+
+    /// Задача отправки холостых сообщений
+    pub cover_task: Mutex<Option<JoinHandle<()>>>,
+
+    /// Открытые тексты, ожидающие нового обмена ключами
+    pub outbox: Mutex<Vec<Zeroizing<Vec<u8>>>>,
+
+    /// Таймер ожидания ответа на начатый нами повторный обмен ключами
+    pub rekey_task: Mutex<Option<JoinHandle<()>>>,
+
+    /// Кандидаты, полученные до установки remote description
+    pub pending_remote_candidates: Mutex<Vec<IceCandidate>>,
+
+    /// Локальные кандидаты этого соединения
+    pub local_candidates: Mutex<Vec<IceCandidate>>,
+
+    /// Флаг активного сбора кандидатов
+    pub collecting_candidates: Mutex<bool>,
+}
+
+impl Session {
+    /// Новая сессия вне менеджера; обычно сессии создаются через `SessionManager::open`
+    pub fn new(id: String, events: Events, manager: Weak<SessionManager>) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            created_at: chrono::Utc::now().timestamp(),
+            events,
+            manager,
+            peer: Mutex::new(None),
+            data_ch: Mutex::new(None),
+            crypto: Mutex::new(None),
+            pending_crypto: Mutex::new(None),
+            remote_identity: Mutex::new(None),
+            bundle_identity: Mutex::new(None),
+            local_signer: Mutex::new(None),
+            remote_signer: Mutex::new(None),
+            identity_state: Mutex::new(None),
+            signaling_key: Mutex::new(None),
+            pake_pending: Mutex::new(None),
+            pake_key: Mutex::new(None),
+            transport_code: Mutex::new(None),
+            pending_offer_id: Mutex::new(None),
+            offered_hello: local_hello(),
+            local_hello: Mutex::new(None),
+            remote_hello: Mutex::new(None),
+            negotiated: Mutex::new(None),
+            early_handshake: Mutex::new(None),
+            my_priv: Mutex::new(None),
+            my_pub: Mutex::new(None),
+            my_kem: Mutex::new(None),
+            pending_kem: Mutex::new(None),
+            send_frame_seq: Mutex::new(0),
+            was_connected: Mutex::new(false),
+            disconnect_task: Mutex::new(None),
+            cover_task: Mutex::new(None),
+            outbox: Mutex::new(Vec::new()),
+            rekey_task: Mutex::new(None),
+            pending_remote_candidates: Mutex::new(Vec::new()),
+            local_candidates: Mutex::new(Vec::new()),
+            collecting_candidates: Mutex::new(false),
+        })
+    }
+
+    /// Зануляет ключи и результаты обмена; соединение и data channel не трогает
+    pub fn clear_keys(&self) {
+        *self.crypto.lock().unwrap() = None;
+        *self.pending_crypto.lock().unwrap() = None;
+        *self.negotiated.lock().unwrap() = None;
+        *self.early_handshake.lock().unwrap() = None;
+        *self.remote_hello.lock().unwrap() = None;
+        *self.remote_identity.lock().unwrap() = None;
+        *self.identity_state.lock().unwrap() = None;
+        *self.my_priv.lock().unwrap() = None;
+        *self.my_pub.lock().unwrap() = None;
+        *self.my_kem.lock().unwrap() = None;
+        *self.pending_kem.lock().unwrap() = None;
+        self.outbox.lock().unwrap().clear();
+        *self.was_connected.lock().unwrap() = false;
+    }
+
+    /// Сбрасывает кандидаты ICE обоих направлений
+    pub fn clear_candidates(&self) {
+        self.pending_remote_candidates.lock().unwrap().clear();
+        self.local_candidates.lock().unwrap().clear();
+        *self.collecting_candidates.lock().unwrap() = false;
+    }
+
+    /// Текущее состояние разговора
+    pub fn state(&self) -> SessionState {
+        if self.disconnect_task.lock().unwrap().is_some() {
+            return SessionState::Recovering;
+        }
+        match self.crypto.lock().unwrap().as_ref() {
+            Some(ctx) if ctx.verified => SessionState::Connected,
+            Some(_) => SessionState::AwaitingVerification,
+            None if *self.collecting_candidates.lock().unwrap() => SessionState::Gathering,
+            None => SessionState::Connecting,
+        }
+    }
+
+    /// Закрывает соединение сессии и зануляет её ключи; другие сессии не затрагиваются
+    pub async fn close(&self) {
+        info!("Closing session {}", self.id);
+
+        // извлекаем data channel и освобождаем мьютекс
+        let dc = self.data_ch.lock().unwrap().take();
+        if let Some(dc) = dc {
+            let _ = dc.close().await;
+        }
+
+        // извлекаем peer connection и освобождаем мьютекс
+        let pc = self.peer.lock().unwrap().take();
+        if let Some(pc) = pc {
+            let _ = pc.close().await;
+        }
+
+        // отменяем отложенный disconnect, если он был
+        if let Some(handle) = self.disconnect_task.lock().unwrap().take() {
+            info!("Aborting pending disconnect task of session {}", self.id);
+            handle.abort();
+        }
+        if let Some(handle) = self.rekey_task.lock().unwrap().take() {
+            handle.abort();
+        }
+
+        // ключи сигнализации и CPace этой сессии больше не нужны
+        debug!("Clearing signaling state of session {}", self.id);
+        *self.signaling_key.lock().unwrap() = None;
+        *self.pake_pending.lock().unwrap() = None;
+        *self.transport_code.lock().unwrap() = None;
+        *self.pending_offer_id.lock().unwrap() = None;
+
+        // зануляем ключи, кандидаты и отправляем событие отключения
+        emit_disconnected(self);
+    }
+}
+
+/// Все разговоры клиента и общая для них рассылка событий
+pub struct SessionManager {
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+
+    /// Сессия, открытая в UI
+    active: Mutex<Option<String>>,
+
+    pub events: Events,
+}
+
+impl SessionManager {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            sessions: Mutex::new(HashMap::new()),
+            active: Mutex::new(None),
+            events: Events::new(),
+        })
+    }
+
+    /// Создаёт и регистрирует сессию
+    ///
+    /// id принятого offer выбирает собеседник, поэтому занятый id - ошибка:
+    /// замена оставила бы прежнюю сессию с открытым соединением без владельца.
+    pub fn open(self: &Arc<Self>, id: String) -> Result<Arc<Session>, SscError> {
+        let session = Session::new(id, self.events.clone(), Arc::downgrade(self));
+        self.insert(session.clone())?;
+        Ok(session)
+    }
+
+    /// Регистрирует сессию, если её id ещё не занят
+    pub fn insert(&self, session: Arc<Session>) -> Result<(), SscError> {
+        match self.sessions.lock().unwrap().entry(session.id.clone()) {
+            Entry::Occupied(_) => Err(SscError::SessionExists(session.id.clone())),
+            Entry::Vacant(slot) => {
+                slot.insert(session);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Result<Arc<Session>, SscError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| SscError::UnknownSession(id.to_string()))
+    }
+
+    /// Убирает именно эту сессию; другая сессия с тем же id не затрагивается
+    pub fn remove(&self, session: &Session) -> Option<Arc<Session>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let registered = sessions
+            .get(&session.id)
+            .is_some_and(|s| std::ptr::eq(Arc::as_ptr(s), session));
+        if !registered {
+            return None;
+        }
+        let mut active = self.active.lock().unwrap();
+        if active.as_deref() == Some(session.id.as_str()) {
+            *active = None;
+        }
+        sessions.remove(&session.id)
+    }
+
+    pub fn all(&self) -> Vec<Arc<Session>> {
+        self.sessions.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Делает сессию активной; сессия должна существовать
+    pub fn set_active(&self, id: &str) -> Result<(), SscError> {
+        let session = self.get(id)?;
+        *self.active.lock().unwrap() = Some(session.id.clone());
+        Ok(())
+    }
+
+    pub fn active(&self) -> Option<String> {
+        self.active.lock().unwrap().clone()
+    }
+
+    /// Список сессий в порядке создания
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let active = self.active();
+        let mut list: Vec<SessionInfo> = self
+            .all()
+            .iter()
+            .map(|session| SessionInfo {
+                session_id: session.id.clone(),
+                state: session.state(),
+                active: active.as_deref() == Some(session.id.as_str()),
+                created_at: session.created_at,
+                identity: *session.identity_state.lock().unwrap(),
+            })
+            .collect();
+        list.sort_by_key(|info| info.created_at);
+        list
+    }
+}
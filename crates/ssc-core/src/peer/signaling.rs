@@ -0,0 +1,499 @@
+use crate::error::SscError;
+use crate::events::{emit_identity_changed, Events};
+use crate::peer::identity::{
+    check_pinned, sign_with_identity, IdentityChanged, PeerIdentity, PinStatus,
+};
+use crate::peer::session::Session;
+use crate::peer::state::{
+    ACCEPTED_OFFER_IDS, IDENTITY_ENABLED, MAX_ACCEPTED_OFFER_IDS, MAX_CLOCK_SKEW_SECS,
+    OFFER_TTL_SECS,
+};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rand::Rng;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+use ring::{hmac, pbkdf2, rand as ring_rand};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::num::NonZeroU32;
+use tracing::info;
+use zeroize::Zeroizing;
+
+/// Ограничение на размер распакованного пакета (защита от zip-bomb)
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024; // 256 KiB
+
+/// Метка подписи пакетов сигнализации
+const SIGNATURE_LABEL: &[u8] = b"ssc-bundle-v1";
+
+/// Префикс зашифрованного пакета (в base64 двоеточие не встречается)
+const ENCRYPTED_PREFIX: &str = "E1:";
+
+/// Параметры шифрования пакета кодом переноса
+const TRANSPORT_SALT_LEN: usize = 16;
+const TRANSPORT_NONCE_LEN: usize = 12;
+const TRANSPORT_PBKDF2_ITERATIONS: u32 = 200_000;
+
+/// Алфавит кода переноса: Crockford base32 без похожих символов
+const CODE_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_GROUPS: usize = 4;
+const CODE_GROUP_LEN: usize = 4;
+
+/// Пакет сигнализации: точные байты JSON плюс подпись и MAC над ними
+///
+/// Подпись и MAC считаются по `body` как есть, а не по пересериализованной
+/// структуре, поэтому проверка не зависит от порядка полей в JSON.
+/// Подпись сессионным ключом сама по себе не доказывает, кто собрал пакет:
+/// ключ приходит в том же пакете (см. `mac_key`). Поэтому ключи подписи обоих
+/// пакетов входят в транскрипт обмена ключами: пакет, переподписанный
+/// посредником, даёт сторонам разные ключи и SAS.
+#[derive(Serialize, Deserialize)]
+struct SignedPacket {
+    body: String,                    // base64 JSON (SdpPayload или ConnectionBundle)
+    signer: String,                  // hex Ed25519 public key
+    installation_id: Option<String>, // есть, если подписано ключом идентичности
+    signature: String,               // hex
+    mac: String,                     // hex HMAC-SHA256
+}
+
+/// Проверка свежести `SdpPayload.ts`: не старше TTL и не из будущего дальше допуска
+pub fn check_freshness(ts: i64) -> Result<(), SscError> {
+    let now = chrono::Utc::now().timestamp();
+    let ttl_secs = *OFFER_TTL_SECS.lock().unwrap();
+    // ts приходит от собеседника: крайние значения не должны переполнять разность
+    let age_secs = now.saturating_sub(ts);
+
+    if age_secs < -MAX_CLOCK_SKEW_SECS {
+        return Err(SscError::FromFuture {
+            ahead_secs: age_secs.saturating_neg(),
+        });
+    }
+    if age_secs > ttl_secs {
+        return Err(SscError::Expired { age_secs, ttl_secs });
+    }
+    Ok(())
+}
+
+/// Offer можно принять только один раз
+pub fn mark_offer_used(id: &str) -> Result<(), SscError> {
+    let mut seen = ACCEPTED_OFFER_IDS.lock().unwrap();
+    if seen.iter().any(|known| known == id) {
+        return Err(SscError::Replayed(id.to_string()));
+    }
+    seen.push_back(id.to_string());
+    // Старые id вытесняются: такие offer всё равно отсекает TTL
+    while seen.len() > MAX_ACCEPTED_OFFER_IDS {
+        seen.pop_front();
+    }
+    Ok(())
+}
+
+/// Offer, на который не удалось ответить (неверный пароль, ошибка WebRTC), можно принять снова
+pub fn unmark_offer_used(id: &str) {
+    ACCEPTED_OFFER_IDS
+        .lock()
+        .unwrap()
+        .retain(|known| known != id);
+}
+
+/// Answer должен соответствовать ожидающему offer этой сессии
+pub fn check_pending_offer(session: &Session, id: &str) -> Result<(), SscError> {
+    match session.pending_offer_id.lock().unwrap().as_deref() {
+        None => Err(SscError::NoPendingOffer),
+        Some(expected) if expected != id => Err(SscError::IdMismatch {
+            expected: expected.to_string(),
+            actual: id.to_string(),
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
+fn signed_message(installation_id: Option<&str>, body: &[u8]) -> Vec<u8> {
+    let mut msg = SIGNATURE_LABEL.to_vec();
+    let id = installation_id.unwrap_or_default();
+    msg.extend_from_slice(&(id.len() as u32).to_be_bytes());
+    msg.extend_from_slice(id.as_bytes());
+    msg.extend_from_slice(body);
+    msg
+}
+
+/// Ключ MAC: код переноса, если пакет им защищён, иначе ключ подписи
+///
+/// Код переноса - единственный секрет, общий для сторон до обмена, и только с ним
+/// MAC подтверждает, что пакет собран знающим код. Без кода ключ выводится из
+/// `signer`, который приходит в том же пакете: такой MAC ловит лишь искажения,
+/// а подмену пакета выявляет транскрипт с ключами подписи (см. `BundleSigner`).
+fn mac_key(signer: &[u8], transport_code: Option<&str>) -> hmac::Key {
+    let derived = match transport_code {
+        Some(code) => Sha256::digest(
+            [
+                b"ssc-bundle-mac-code".as_slice(),
+                normalize_code(code).as_bytes(),
+                signer,
+            ]
+            .concat(),
+        ),
+        None => Sha256::digest([b"ssc-bundle-mac".as_slice(), signer].concat()),
+    };
+    hmac::Key::new(hmac::HMAC_SHA256, &derived)
+}
+
+/// Кем подписан пакет сигнализации
+pub struct BundleSigner {
+    /// Открытый ключ подписи, входит в транскрипт обмена ключами
+    pub key: [u8; 32],
+    /// Идентичность, если пакет подписан ключом идентичности
+    pub identity: Option<PeerIdentity>,
+}
+
+/// Подпись ключом идентичности или сессионным ключом сигнализации
+fn sign(session: &Session, body: &[u8]) -> Option<(Vec<u8>, Option<String>, Vec<u8>)> {
+    if *IDENTITY_ENABLED.lock().unwrap() {
+        let (installation_id, public, sig) =
+            sign_with_identity(|id| signed_message(Some(id), body))?;
+        return Some((public.to_vec(), Some(installation_id), sig));
+    }
+
+    let mut key = session.signaling_key.lock().unwrap();
+    if key.is_none() {
+        let rng = ring_rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).ok()?;
+        *key = Some(Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).ok()?);
+    }
+    let key = key.as_ref()?;
+    let sig = key.sign(&signed_message(None, body));
+    Some((
+        key.public_key().as_ref().to_vec(),
+        None,
+        sig.as_ref().to_vec(),
+    ))
+}
+
+/// Одноразовый код переноса: 16 символов base32 (80 бит) группами по 4
+pub fn generate_transport_code() -> String {
+    let mut rng = rand::rng();
+    (0..CODE_GROUPS)
+        .map(|_| {
+            (0..CODE_GROUP_LEN)
+                .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Код без разделителей и регистра, как его мог набрать пользователь
+fn normalize_code(code: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect(),
+    )
+}
+
+/// Ключ шифрования пакета: PBKDF2-HMAC-SHA256 от кода и случайной соли
+fn transport_key(code: &str, salt: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(TRANSPORT_PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        normalize_code(code).as_bytes(),
+        &mut key[..],
+    );
+    key
+}
+
+/// Шифрует сжатый пакет: `salt (16) || nonce (12) || ciphertext`
+fn encrypt_packet(code: &str, packet: &[u8]) -> Result<Vec<u8>, SscError> {
+    let salt = rand::rng().random::<[u8; TRANSPORT_SALT_LEN]>();
+    let nonce = rand::rng().random::<[u8; TRANSPORT_NONCE_LEN]>();
+    let key = transport_key(code, &salt);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+        .encrypt(Nonce::from_slice(&nonce), packet)
+        .map_err(|_| SscError::Crypto("bundle encryption failed".into()))?;
+
+    Ok([&salt[..], &nonce[..], &ciphertext].concat())
+}
+
+fn decrypt_packet(code: &str, data: &[u8]) -> Result<Vec<u8>, SscError> {
+    if data.len() < TRANSPORT_SALT_LEN + TRANSPORT_NONCE_LEN {
+        return Err(SscError::BadTransportCode);
+    }
+    let (salt, rest) = data.split_at(TRANSPORT_SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(TRANSPORT_NONCE_LEN);
+    let key = transport_key(code, salt);
+    ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SscError::BadTransportCode)
+}
+
+/// JSON -> подпись и MAC -> GZIP -> [шифрование кодом переноса] -> base64
+///
+/// С кодом переноса из пакета не видно ничего, включая SDP и адреса кандидатов.
+pub fn seal_bundle<T: Serialize>(
+    session: &Session,
+    value: &T,
+    transport_code: Option<&str>,
+) -> Result<String, SscError> {
+    let body = serde_json::to_vec(value).map_err(|e| SscError::Decode(e.to_string()))?;
+    let (signer, installation_id, sig) =
+        sign(session, &body).ok_or_else(|| SscError::Crypto("signing key unavailable".into()))?;
+    let mac = hmac::sign(&mac_key(&signer, transport_code), &body);
+    *session.local_signer.lock().unwrap() = <[u8; 32]>::try_from(&signer[..]).ok();
+
+    let packet = SignedPacket {
+        body: general_purpose::STANDARD.encode(&body),
+        signer: hex::encode(&signer),
+        installation_id,
+        signature: hex::encode(sig),
+        mac: hex::encode(mac.as_ref()),
+    };
+
+    let json = serde_json::to_vec(&packet).map_err(|e| SscError::Decode(e.to_string()))?;
+    let mut gz = GzEncoder::new(Vec::new(), Compression::fast());
+    let compressed = gz
+        .write_all(&json)
+        .and_then(|_| gz.finish())
+        .map_err(|e| SscError::Decode(e.to_string()))?;
+    Ok(match transport_code {
+        Some(code) => format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            general_purpose::STANDARD.encode(encrypt_packet(code, &compressed)?)
+        ),
+        None => general_purpose::STANDARD.encode(compressed),
+    })
+}
+
+/// base64 -> [расшифровка] -> GUNZIP -> проверка подписи и MAC -> JSON
+///
+/// Ничего из пакета не возвращается, пока обе проверки не пройдены.
+/// `session_id` - сессия, к которой относится пакет, если она уже известна;
+/// смена ключа идентичности контакта публикуется в `events`.
+pub fn open_bundle<T: DeserializeOwned>(
+    s: &str,
+    transport_code: Option<&str>,
+    events: &Events,
+    session_id: Option<&str>,
+) -> Result<T, SscError> {
+    open_signed_bundle(s, transport_code, events, session_id).map(|(value, _)| value)
+}
+
+/// Как `open_bundle`, но ещё возвращает ключ и идентичность, которыми подписан пакет
+///
+/// Хранилище контактов только читается: закрепляет ключ лишь сверка SAS.
+pub fn open_signed_bundle<T: DeserializeOwned>(
+    s: &str,
+    transport_code: Option<&str>,
+    events: &Events,
+    session_id: Option<&str>,
+) -> Result<(T, BundleSigner), SscError> {
+    let encoding = |e: &dyn std::fmt::Display| SscError::Decode(e.to_string());
+
+    let s = s.trim();
+    let compressed = match (s.strip_prefix(ENCRYPTED_PREFIX), transport_code) {
+        (Some(encrypted), Some(code)) => {
+            let data = general_purpose::STANDARD
+                .decode(encrypted)
+                .map_err(|e| encoding(&e))?;
+            decrypt_packet(code, &data)?
+        }
+        (Some(_), None) => return Err(SscError::TransportCodeRequired),
+        (None, Some(_)) => return Err(SscError::NotEncrypted),
+        (None, None) => general_purpose::STANDARD
+            .decode(s)
+            .map_err(|e| encoding(&e))?,
+    };
+    // Читаем на байт больше лимита: так превышение отличается от пакета ровно в лимит
+    let mut json = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut json)
+        .map_err(|e| encoding(&e))?;
+    if json.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(SscError::DecompressionLimit {
+            limit: MAX_DECOMPRESSED_SIZE,
+        });
+    }
+    let packet: SignedPacket = serde_json::from_slice(&json).map_err(|e| encoding(&e))?;
+
+    let body = general_purpose::STANDARD
+        .decode(&packet.body)
+        .map_err(|e| encoding(&e))?;
+    let signer = hex::decode(&packet.signer).map_err(|e| encoding(&e))?;
+    let signer = <[u8; 32]>::try_from(&signer[..])
+        .map_err(|_| SscError::Decode("signer key length".into()))?;
+    let sig = hex::decode(&packet.signature).map_err(|e| encoding(&e))?;
+    let mac = hex::decode(&packet.mac).map_err(|e| encoding(&e))?;
+
+    signature::UnparsedPublicKey::new(&signature::ED25519, &signer)
+        .verify(
+            &signed_message(packet.installation_id.as_deref(), &body),
+            &sig,
+        )
+        .map_err(|_| SscError::BadSignature)?;
+    hmac::verify(&mac_key(&signer, transport_code), &body, &mac).map_err(|_| SscError::BadMac)?;
+
+    // Пакет подписан ключом идентичности - сверяем с закреплёнными контактами
+    let mut identity = None;
+    if let Some(installation_id) = packet.installation_id {
+        let peer = PeerIdentity {
+            installation_id,
+            identity_key: signer,
+        };
+        if let PinStatus::Changed { previous } = check_pinned(&peer) {
+            emit_identity_changed(
+                events,
+                session_id,
+                &IdentityChanged {
+                    installation_id: peer.installation_id.clone(),
+                    previous_fingerprint: previous.fingerprint,
+                    new_fingerprint: peer.fingerprint(),
+                },
+            );
+            return Err(SscError::IdentityChanged(peer.fingerprint()));
+        }
+        info!("Bundle signed by identity {}", peer.fingerprint());
+        identity = Some(peer);
+    }
+
+    let value = serde_json::from_slice(&body).map_err(|e| encoding(&e))?;
+    Ok((
+        value,
+        BundleSigner {
+            key: signer,
+            identity,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Weak};
+
+    fn test_session() -> Arc<Session> {
+        Session::new("test".into(), Events::new(), Weak::new())
+    }
+
+    fn open(s: &str, transport_code: Option<&str>) -> Result<String, SscError> {
+        open_bundle(s, transport_code, &Events::new(), None)
+    }
+
+    fn gzip_base64(data: &[u8]) -> String {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::fast());
+        gz.write_all(data).unwrap();
+        general_purpose::STANDARD.encode(gz.finish().unwrap())
+    }
+
+    /// Пакет без кода переноса в разобранном виде
+    fn unpack(sealed: &str) -> SignedPacket {
+        let compressed = general_purpose::STANDARD.decode(sealed).unwrap();
+        let mut json = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut json)
+            .unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    fn pack(packet: &SignedPacket) -> String {
+        gzip_base64(&serde_json::to_vec(packet).unwrap())
+    }
+
+    #[test]
+    fn bundle_roundtrip_with_and_without_transport_code() {
+        let session = test_session();
+        let sealed = seal_bundle(&session, &"hello", None).unwrap();
+        assert_eq!(open(&sealed, None).unwrap(), "hello");
+
+        let code = generate_transport_code();
+        let sealed = seal_bundle(&session, &"hello", Some(&code)).unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_PREFIX));
+        assert_eq!(open(&sealed, Some(&code.to_lowercase())).unwrap(), "hello");
+    }
+
+    #[test]
+    fn tampered_bundle_is_rejected() {
+        let session = test_session();
+        let sealed = seal_bundle(&session, &"hello", None).unwrap();
+        assert!(open(&pack(&unpack(&sealed)), None).is_ok());
+
+        let mut packet = unpack(&sealed);
+        packet.body = general_purpose::STANDARD.encode(b"\"bye\"");
+        assert!(matches!(
+            open(&pack(&packet), None),
+            Err(SscError::BadSignature)
+        ));
+
+        let mut packet = unpack(&sealed);
+        packet.mac = hex::encode([0u8; 32]);
+        assert!(matches!(open(&pack(&packet), None), Err(SscError::BadMac)));
+
+        // Подмена ключа подписи: подпись под телом уже не сходится
+        let mut packet = unpack(&sealed);
+        packet.signer = unpack(&seal_bundle(&test_session(), &"bye", None).unwrap()).signer;
+        assert!(matches!(
+            open(&pack(&packet), None),
+            Err(SscError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn transport_code_mismatch_is_rejected() {
+        let session = test_session();
+        let code = generate_transport_code();
+        let encrypted = seal_bundle(&session, &"hello", Some(&code)).unwrap();
+        let plain = seal_bundle(&session, &"hello", None).unwrap();
+
+        assert!(matches!(
+            open(&encrypted, None),
+            Err(SscError::TransportCodeRequired)
+        ));
+        assert!(matches!(
+            open(&encrypted, Some(&generate_transport_code())),
+            Err(SscError::BadTransportCode)
+        ));
+        assert!(matches!(
+            open(&plain, Some(&code)),
+            Err(SscError::NotEncrypted)
+        ));
+    }
+
+    #[test]
+    fn oversized_bundle_hits_decompression_limit() {
+        let bomb = gzip_base64(&vec![b' '; MAX_DECOMPRESSED_SIZE as usize + 1]);
+        assert!(matches!(
+            open(&bomb, None),
+            Err(SscError::DecompressionLimit {
+                limit: MAX_DECOMPRESSED_SIZE
+            })
+        ));
+
+        // Ровно в лимит - не ошибка лимита, дальше пакет просто не разбирается
+        let exact = gzip_base64(&vec![b' '; MAX_DECOMPRESSED_SIZE as usize]);
+        assert!(matches!(open(&exact, None), Err(SscError::Decode(_))));
+    }
+
+    #[test]
+    fn freshness_accepts_current_timestamp() {
+        assert!(check_freshness(chrono::Utc::now().timestamp()).is_ok());
+    }
+
+    #[test]
+    fn freshness_rejects_extreme_timestamps_without_overflow() {
+        assert!(matches!(
+            check_freshness(i64::MIN),
+            Err(SscError::Expired { .. })
+        ));
+        assert!(matches!(
+            check_freshness(i64::MAX),
+            Err(SscError::FromFuture { .. })
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::peer::identity::Identity;
+use crate::peer::traffic::PaddingPolicy;
+use crate::peer::types::ServerConfig;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// ========== GLOBAL STATE ==========
+//
+// Настройки и данные всего приложения; состояние разговоров - в `peer::session`.
+
+/// Не отправлять сообщения, пока пользователь не подтвердил SAS
+pub static REQUIRE_VERIFICATION: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Подписывать эфемерный ключ долговременным ключом идентичности
+pub static IDENTITY_ENABLED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Ключ идентичности этой установки (загружается с диска при первом использовании)
+pub static LOCAL_IDENTITY: Lazy<Mutex<Option<Identity>>> = Lazy::new(|| Mutex::new(None));
+
+/// id уже принятых offer (защита от повторного использования)
+pub static ACCEPTED_OFFER_IDS: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Время жизни offer/answer в секундах
+pub static OFFER_TTL_SECS: Lazy<Mutex<i64>> = Lazy::new(|| Mutex::new(DEFAULT_OFFER_TTL_SECS));
+
+/// Каталог данных приложения (ключ идентичности, закреплённые контакты)
+pub static DATA_DIR: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// Предлагать Double Ratchet при согласовании (выбирается, если его предложат обе стороны)
+pub static DOUBLE_RATCHET_ENABLED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Предлагать гибридный обмен X25519 + ML-KEM-768 (выбирается, если его предложат обе стороны)
+pub static PQ_HYBRID_ENABLED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Выравнивание длины сообщений перед шифрованием
+pub static PADDING_POLICY: Lazy<Mutex<PaddingPolicy>> =
+    Lazy::new(|| Mutex::new(PaddingPolicy::PowerOfTwo));
+
+/// Средний интервал холостых сообщений (None - выключены)
+pub static COVER_TRAFFIC_INTERVAL: Lazy<Mutex<Option<Duration>>> = Lazy::new(|| Mutex::new(None));
+
+/// Порог сообщений на один обмен ключами, после которого шифрование запрещено
+pub static NONCE_LIMIT: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(DEFAULT_NONCE_LIMIT));
+
+/// Печатать в лог тексты сообщений, ключи и учётные данные (не сохраняется между запусками)
+pub static UNSAFE_DIAGNOSTICS: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+/// Глобальное хранилище для пользовательских ICE серверов
+pub static USER_ICE_SERVERS: Lazy<Mutex<Option<Vec<ServerConfig>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+// ========== CONSTANTS ==========
+
+/// Длина тега аутентификации для ChaCha20-Poly1305
+pub const TAG_LEN: usize = 16;
+
+/// Длина номера эпохи храповика в заголовке зашифрованного сообщения
+pub const EPOCH_LEN: usize = 4;
+
+/// Длина явного sequence number в заголовке зашифрованного сообщения
+pub const SEQ_LEN: usize = 8;
+
+/// Размер окна защиты от replay (в сообщениях)
+pub const REPLAY_WINDOW_SIZE: u64 = 64;
+
+/// Количество сообщений, после которого ключ отправки сдвигается
+pub const RATCHET_AFTER_MESSAGES: u64 = 1000;
+
+/// Время, после которого ключ отправки сдвигается
+pub const RATCHET_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// На сколько эпох вперёд получатель может догнать отправителя
+pub const MAX_EPOCH_LAG: u32 = 4;
+
+/// Порог сообщений на один обмен ключами по умолчанию
+///
+/// Счётчик nonce сам по себе не переполнится и при `u64`, поэтому порог
+/// выбран так, чтобы повторный обмен ключами действительно случался
+/// в долгих разговорах и ограничивал объём данных под одним набором ключей.
+pub const DEFAULT_NONCE_LIMIT: u64 = 1 << 16;
+
+/// Сколько сообщений может ждать завершения нового обмена ключами
+pub const MAX_QUEUED_MESSAGES: usize = 256;
+
+/// Сколько ждать ответа собеседника на наш повторный обмен ключами
+pub const REKEY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Сколько сообщений одной цепочки Double Ratchet можно пропустить
+pub const MAX_SKIP: u32 = 1000;
+
+/// Максимальное число хранимых ключей пропущенных сообщений
+pub const MAX_SKIPPED_KEYS: usize = 2000;
+
+/// Период ожидания перед принудительным отключением
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Время жизни offer/answer по умолчанию (совпадает с TTL QR-кода в UI)
+pub const DEFAULT_OFFER_TTL_SECS: i64 = 5 * 60;
+
+/// Допустимое расхождение часов между устройствами
+pub const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+/// Сколько id принятых offer помнить
+pub const MAX_ACCEPTED_OFFER_IDS: usize = 256;
+
+/// Минимальная длина внутреннего кадра при выравнивании до степени двойки
+pub const MIN_PADDED_LEN: usize = 32;
+
+/// Наибольший размер блока или случайного дополнения
+pub const MAX_PADDING: usize = 64 * 1024;
+
+/// Минимальный интервал холостых сообщений
+pub const MIN_COVER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Сколько событий может накопиться у медленного подписчика
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
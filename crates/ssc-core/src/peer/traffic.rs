@@ -0,0 +1,199 @@
+use crate::peer::crypto::SealError;
+use crate::peer::data_channel::{send_frame, start_rekey, MessageType};
+use crate::peer::negotiation::PADDED_VERSION;
+use crate::peer::session::Session;
+use crate::peer::state::{
+    COVER_TRAFFIC_INTERVAL, MIN_PADDED_LEN, PADDING_POLICY, REQUIRE_VERIFICATION,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::time::sleep;
+use tracing::info;
+
+/// Заголовок внутреннего кадра: kind (1) | len (4 BE)
+const INNER_HEADER_LEN: usize = 5;
+
+/// Как выравнивать длину открытого текста перед шифрованием
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum PaddingPolicy {
+    /// Без выравнивания: длина шифротекста повторяет длину сообщения
+    None,
+    /// До ближайшей степени двойки (не меньше `MIN_PADDED_LEN`)
+    PowerOfTwo,
+    /// До кратного `size` байт
+    Block { size: usize },
+    /// Случайное дополнение от 0 до `max` байт
+    Random { max: usize },
+}
+
+impl PaddingPolicy {
+    /// Длина внутреннего кадра после выравнивания
+    fn padded_len(&self, len: usize) -> usize {
+        match *self {
+            Self::None => len,
+            Self::PowerOfTwo => len.next_power_of_two().max(MIN_PADDED_LEN),
+            Self::Block { size } if size > 0 => len.div_ceil(size) * size,
+            Self::Block { .. } => len,
+            Self::Random { max } => len + rand::rng().random_range(0..=max),
+        }
+    }
+}
+
+/// Тип внутреннего кадра: отличить холостое сообщение может только получатель
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InnerKind {
+    /// Сообщение чата
+    Text = 0,
+    /// Холостое сообщение, получатель молча отбрасывает его
+    Cover = 1,
+}
+
+/// Внутренний кадр: `kind (1) || len (4 BE) || body || нули до выровненной длины`
+///
+/// Кадр целиком шифруется AEAD, поэтому выравнивание защищено так же, как текст.
+pub fn frame(kind: InnerKind, body: &[u8]) -> Vec<u8> {
+    let policy = *PADDING_POLICY.lock().unwrap();
+    let padded_len = policy.padded_len(INNER_HEADER_LEN + body.len());
+    let mut out = Vec::with_capacity(padded_len);
+    out.push(kind as u8);
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(body);
+    out.resize(padded_len, 0);
+    out
+}
+
+/// Разбор внутреннего кадра после расшифровки
+pub fn unframe(data: &[u8]) -> Option<(InnerKind, &[u8])> {
+    if data.len() < INNER_HEADER_LEN {
+        return None;
+    }
+    let kind = match data[0] {
+        0 => InnerKind::Text,
+        1 => InnerKind::Cover,
+        _ => return None,
+    };
+    let len = u32::from_be_bytes(data[1..INNER_HEADER_LEN].try_into().unwrap()) as usize;
+    let body = data[INNER_HEADER_LEN..].get(..len)?;
+    Some((kind, body))
+}
+
+/// Используется ли внутренний кадр в сессии (версия протокола 2 и выше)
+pub fn framing_enabled(session: &Session) -> bool {
+    session
+        .negotiated
+        .lock()
+        .unwrap()
+        .is_some_and(|n| n.version >= PADDED_VERSION)
+}
+
+/// Шифруем и отправляем одно холостое сообщение
+async fn send_cover(session: &Arc<Session>) -> bool {
+    let sealed = {
+        let mut lock = session.crypto.lock().unwrap();
+        let Some(ref mut ctx) = *lock else {
+            return false;
+        };
+        if !ctx.verified && *REQUIRE_VERIFICATION.lock().unwrap() {
+            return true;
+        }
+        ctx.seal(&frame(InnerKind::Cover, &[]))
+    };
+
+    match sealed {
+        Ok((_, _, ciphertext)) => send_frame(session, MessageType::ChatText, ciphertext).await,
+        // Холостые сообщения тоже расходуют лимит: обновляем ключи, а не замолкаем
+        Err(SealError::Exhausted(_)) => start_rekey(session).await,
+        Err(_) => true,
+    }
+}
+
+/// Перезапускает отправку холостых сообщений сессии по текущим настройкам
+///
+/// Интервал между сообщениями случайный, от половины до полутора заданных,
+/// чтобы поток не выделялся строгим периодом.
+pub fn restart_cover_traffic(session: &Arc<Session>) {
+    stop_cover_traffic(session);
+
+    let Some(interval) = *COVER_TRAFFIC_INTERVAL.lock().unwrap() else {
+        return;
+    };
+    if session.crypto.lock().unwrap().is_none() || !framing_enabled(session) {
+        return;
+    }
+
+    info!(
+        "Starting cover traffic for session {} every ~{:?}",
+        session.id, interval
+    );
+    // Задача хранится в самой сессии, поэтому держит только слабую ссылку
+    let weak = Arc::downgrade(session);
+    let handle = tokio::spawn(async move {
+        loop {
+            let jitter = rand::rng().random_range(0.5..1.5);
+            sleep(interval.mul_f64(jitter)).await;
+            let Some(session) = weak.upgrade() else {
+                break;
+            };
+            if !send_cover(&session).await {
+                info!("Cover traffic stopped: session {} has no keys", session.id);
+                break;
+            }
+        }
+    });
+    *session.cover_task.lock().unwrap() = Some(handle);
+}
+
+/// Останавливает отправку холостых сообщений сессии
+pub fn stop_cover_traffic(session: &Session) {
+    if let Some(handle) = session.cover_task.lock().unwrap().take() {
+        handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_roundtrip_keeps_kind_and_body() {
+        for (kind, body) in [
+            (InnerKind::Text, &b"hello"[..]),
+            (InnerKind::Cover, &b""[..]),
+        ] {
+            let framed = frame(kind, body);
+            assert!(framed.len() >= INNER_HEADER_LEN + body.len());
+            assert_eq!(unframe(&framed), Some((kind, body)));
+        }
+    }
+
+    #[test]
+    fn unframe_rejects_truncated_and_unknown_frames() {
+        assert_eq!(unframe(&[]), None);
+        assert_eq!(unframe(&[0, 0, 0]), None);
+        // Длина в заголовке больше, чем данных в кадре
+        assert_eq!(unframe(&[0, 0, 0, 0, 4, b'a', b'b']), None);
+        assert_eq!(unframe(&[0, 0xff, 0xff, 0xff, 0xff]), None);
+        // Неизвестный тип кадра
+        assert_eq!(unframe(&[7, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn unframe_ignores_padding() {
+        let data = [0, 0, 0, 0, 2, b'o', b'k', 0, 0, 0];
+        assert_eq!(unframe(&data), Some((InnerKind::Text, &b"ok"[..])));
+    }
+
+    #[test]
+    fn padded_len_follows_policy() {
+        assert_eq!(PaddingPolicy::None.padded_len(10), 10);
+        assert_eq!(PaddingPolicy::PowerOfTwo.padded_len(10), MIN_PADDED_LEN);
+        assert_eq!(PaddingPolicy::PowerOfTwo.padded_len(100), 128);
+        assert_eq!(PaddingPolicy::Block { size: 64 }.padded_len(65), 128);
+        assert_eq!(PaddingPolicy::Block { size: 0 }.padded_len(65), 65);
+        let random = PaddingPolicy::Random { max: 16 }.padded_len(10);
+        assert!((10..=26).contains(&random));
+    }
+}
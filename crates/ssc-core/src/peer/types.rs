@@ -0,0 +1,72 @@
+use crate::logger::Secret;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+/// Полезная нагрузка SDP с метаданными
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SdpPayload {
+    pub sdp: RTCSessionDescription,
+    pub id: String,
+    pub ts: i64,
+}
+
+/// ICE кандидат для WebRTC соединения
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IceCandidate {
+    pub candidate: String,
+    pub sdp_mid: Option<String>,
+    pub sdp_mline_index: Option<u16>,
+    pub connection_id: String, // ID соединения для сопоставления
+}
+
+/// Полный пакет соединения с SDP и кандидатами
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConnectionBundle {
+    pub sdp_payload: SdpPayload,
+    pub ice_candidates: Vec<IceCandidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pake: Option<String>, // hex сообщение CPace, если offer защищён паролем
+}
+
+/// Закодированный offer/answer и сессия, к которой он относится
+#[derive(Serialize, Debug, Clone)]
+pub struct SignalingBundle {
+    pub session_id: String,
+    pub encoded: String,
+}
+
+/// Что стало с сообщением после `Client::send`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStatus {
+    /// Зашифровано и отправлено
+    Sent,
+    /// Лимит сообщений исчерпан: ждёт нового обмена ключами и уйдёт после него
+    Queued,
+}
+
+/// Конфигурация ICE сервера
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServerConfig {
+    pub id: String,
+    pub r#type: String, // 'stun' or 'turn'
+    pub url: String,
+    pub username: Option<String>,
+    pub credential: Option<String>,
+}
+
+/// Учётные данные TURN в логах скрыты
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let username = self.username.as_deref().map(|u| Secret(u).to_string());
+        let credential = self.credential.as_deref().map(|c| Secret(c).to_string());
+        f.debug_struct("ServerConfig")
+            .field("id", &self.id)
+            .field("type", &self.r#type)
+            .field("url", &self.url)
+            .field("username", &username)
+            .field("credential", &credential)
+            .finish()
+    }
+}
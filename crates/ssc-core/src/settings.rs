@@ -0,0 +1,172 @@
+use crate::error::SscError;
+use crate::logger::unsafe_diagnostics;
+use crate::peer::identity::ensure_identity;
+use crate::peer::state::{
+    COVER_TRAFFIC_INTERVAL, DATA_DIR, DOUBLE_RATCHET_ENABLED, IDENTITY_ENABLED, MAX_PADDING,
+    MIN_COVER_INTERVAL, NONCE_LIMIT, OFFER_TTL_SECS, PADDING_POLICY, PQ_HYBRID_ENABLED,
+    REQUIRE_VERIFICATION, UNSAFE_DIAGNOSTICS,
+};
+use crate::peer::traffic::PaddingPolicy;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// Настройки всего процесса: действуют на все клиенты и следующие соединения
+
+/// Каталог для ключа идентичности и закреплённых контактов
+pub fn set_data_dir(dir: PathBuf) {
+    info!("Data directory: {}", dir.display());
+    *DATA_DIR.lock().unwrap() = Some(dir);
+}
+
+/// Каталог данных; создаётся при первом обращении
+pub fn data_dir() -> Result<PathBuf, SscError> {
+    let dir = DATA_DIR
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| SscError::Storage("data directory is not set".into()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| SscError::Storage(e.to_string()))?;
+    Ok(dir)
+}
+
+/// Требовать подтверждения SAS перед отправкой сообщений
+pub fn set_require_verification(enabled: bool) {
+    info!("Require fingerprint verification: {}", enabled);
+    *REQUIRE_VERIFICATION.lock().unwrap() = enabled;
+}
+
+pub fn require_verification() -> bool {
+    *REQUIRE_VERIFICATION.lock().unwrap()
+}
+
+/// Предлагать Double Ratchet в Hello следующих соединений (используется, если его предложат обе стороны)
+pub fn set_double_ratchet(enabled: bool) {
+    info!("Double Ratchet for new sessions: {}", enabled);
+    *DOUBLE_RATCHET_ENABLED.lock().unwrap() = enabled;
+}
+
+pub fn double_ratchet() -> bool {
+    *DOUBLE_RATCHET_ENABLED.lock().unwrap()
+}
+
+/// Предлагать гибридный обмен X25519 + ML-KEM-768 в Hello следующих соединений
+///
+/// Собеседник без поддержки ML-KEM его не предложит, и сессия останется на X25519.
+pub fn set_pq_hybrid(enabled: bool) {
+    info!("Hybrid X25519 + ML-KEM-768 for new sessions: {}", enabled);
+    *PQ_HYBRID_ENABLED.lock().unwrap() = enabled;
+}
+
+pub fn pq_hybrid() -> bool {
+    *PQ_HYBRID_ENABLED.lock().unwrap()
+}
+
+/// Печатать ли в лог тексты сообщений, ключи и учётные данные
+///
+/// Только для отладки: флаг живёт до перезапуска и нигде не сохраняется.
+pub fn set_unsafe_diagnostics(enabled: bool) {
+    if enabled {
+        warn!("unsafe diagnostics enabled, logs will contain messages and secrets");
+    }
+    *UNSAFE_DIAGNOSTICS.lock().unwrap() = enabled;
+    if !enabled {
+        info!("Unsafe diagnostics disabled");
+    }
+}
+
+pub fn get_unsafe_diagnostics() -> bool {
+    unsafe_diagnostics()
+}
+
+/// Политика выравнивания длины сообщений
+pub fn set_padding_policy(policy: PaddingPolicy) -> Result<(), SscError> {
+    let valid = match policy {
+        PaddingPolicy::Block { size } => (1..=MAX_PADDING).contains(&size),
+        PaddingPolicy::Random { max } => max <= MAX_PADDING,
+        PaddingPolicy::None | PaddingPolicy::PowerOfTwo => true,
+    };
+    if !valid {
+        warn!("Rejected padding policy {:?}", policy);
+        return Err(SscError::InvalidArgument(format!(
+            "padding size must be within 1..={} bytes",
+            MAX_PADDING
+        )));
+    }
+    info!("Padding policy: {:?}", policy);
+    *PADDING_POLICY.lock().unwrap() = policy;
+    Ok(())
+}
+
+pub fn padding_policy() -> PaddingPolicy {
+    *PADDING_POLICY.lock().unwrap()
+}
+
+/// Средний интервал холостых сообщений в миллисекундах, 0 выключает их
+///
+/// Уже установленные сессии подхватывают его через `Client::set_cover_traffic`.
+pub fn set_cover_traffic(interval_ms: u64) -> Result<(), SscError> {
+    let interval =
+        (interval_ms > 0).then(|| Duration::from_millis(interval_ms).max(MIN_COVER_INTERVAL));
+    info!("Cover traffic interval: {:?}", interval);
+    *COVER_TRAFFIC_INTERVAL.lock().unwrap() = interval;
+    Ok(())
+}
+
+/// Интервал холостых сообщений в миллисекундах (0 - выключены)
+pub fn cover_traffic() -> u64 {
+    COVER_TRAFFIC_INTERVAL
+        .lock()
+        .unwrap()
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Порог сообщений на один обмен ключами
+pub fn set_nonce_limit(limit: u64) -> Result<(), SscError> {
+    if limit == 0 {
+        warn!("Nonce limit must be greater than zero");
+        return Err(SscError::InvalidArgument(
+            "nonce limit must be greater than zero".into(),
+        ));
+    }
+    info!("Nonce limit set to {}", limit);
+    *NONCE_LIMIT.lock().unwrap() = limit;
+    Ok(())
+}
+
+pub fn nonce_limit() -> u64 {
+    *NONCE_LIMIT.lock().unwrap()
+}
+
+/// Время жизни offer/answer в секундах
+pub fn set_offer_ttl(secs: i64) -> Result<(), SscError> {
+    if secs <= 0 {
+        warn!("Offer TTL must be greater than zero");
+        return Err(SscError::InvalidArgument(
+            "offer TTL must be greater than zero".into(),
+        ));
+    }
+    info!("Offer TTL set to {}s", secs);
+    *OFFER_TTL_SECS.lock().unwrap() = secs;
+    Ok(())
+}
+
+pub fn offer_ttl() -> i64 {
+    *OFFER_TTL_SECS.lock().unwrap()
+}
+
+/// Подпись эфемерного ключа долговременным ключом для следующих соединений
+pub fn set_identity_enabled(enabled: bool) -> Result<(), SscError> {
+    if enabled && !ensure_identity() {
+        warn!("Identity key storage unavailable, keeping identity disabled");
+        return Err(SscError::Storage("identity key storage unavailable".into()));
+    }
+    info!("Identity keys for new sessions: {}", enabled);
+    *IDENTITY_ENABLED.lock().unwrap() = enabled;
+    Ok(())
+}
+
+pub fn identity_enabled() -> bool {
+    *IDENTITY_ENABLED.lock().unwrap()
+}
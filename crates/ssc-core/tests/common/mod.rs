@@ -0,0 +1,164 @@
+//! Общая обвязка тестов: два клиента в одном процессе, только host-кандидаты.
+#![allow(dead_code)]
+
+use ssc_core::peer::connection::set_ice_servers;
+use ssc_core::settings::nonce_limit;
+use ssc_core::{Client, Event};
+use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::time::timeout;
+
+/// Сколько ждать события, прежде чем считать тест проваленным
+pub const EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Сколько ждать, чтобы убедиться, что события не будет
+pub const QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+pub struct Peer {
+    pub client: Client,
+    pub events: Receiver<Event>,
+}
+
+impl Peer {
+    pub fn new() -> Self {
+        let client = Client::new();
+        let events = client.subscribe();
+        Self { client, events }
+    }
+
+    /// Следующее событие, для которого `matches` вернул true; остальные пропускаются
+    pub async fn wait_for(&mut self, what: &str, matches: impl Fn(&Event) -> bool) -> Event {
+        let wait = async {
+            loop {
+                match self.events.recv().await {
+                    Ok(event) if matches(&event) => return event,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => panic!("event stream closed waiting for {}", what),
+                }
+            }
+        };
+        timeout(EVENT_TIMEOUT, wait)
+            .await
+            .unwrap_or_else(|_| panic!("timed out waiting for {}", what))
+    }
+
+    /// Сообщение собеседника, пришедшее в течение `QUIET_PERIOD`
+    pub async fn message_within_quiet_period(&mut self) -> Option<String> {
+        timeout(QUIET_PERIOD, async {
+            loop {
+                match self.events.recv().await {
+                    Ok(Event::Message { text, .. }) => return Some(text),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    pub async fn wait_message(&mut self) -> String {
+        match self
+            .wait_for("message", |e| matches!(e, Event::Message { .. }))
+            .await
+        {
+            Event::Message { text, .. } => text,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Без STUN/TURN в пакетах только host-кандидаты, сеть наружу не нужна
+pub fn host_candidates_only() {
+    set_ice_servers(Vec::new()).unwrap();
+}
+
+/// Alice создаёт offer, Bob отвечает, обе стороны дожидаются согласования ключей
+pub async fn connect() -> (Peer, Peer, String) {
+    connect_with(|| {}).await
+}
+
+/// Как `connect`, но `between` вызывается после создания сессии Alice
+/// и до сессии Bob: так стороны получают разные настройки
+pub async fn connect_with(between: impl FnOnce()) -> (Peer, Peer, String) {
+    host_candidates_only();
+    let mut alice = Peer::new();
+    let mut bob = Peer::new();
+
+    let offer = alice.client.create_offer(None, None).await.unwrap();
+    between();
+    let answer = bob
+        .client
+        .accept_offer(&offer.encoded, None, None)
+        .await
+        .unwrap();
+    assert_eq!(offer.session_id, answer.session_id);
+    alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await
+        .unwrap();
+
+    let id = offer.session_id;
+    alice
+        .wait_for("alice connected", |e| matches!(e, Event::Connected { .. }))
+        .await;
+    bob.wait_for("bob connected", |e| matches!(e, Event::Connected { .. }))
+        .await;
+    (alice, bob, id)
+}
+
+/// Обе стороны отключаются, не дойдя до Connected, и хотя бы одна видит
+/// провал подтверждения ключей
+///
+/// Первая проверившая тег сторона закрывает сессию, и тег, ещё не дошедший
+/// до второй стороны, может потеряться: тогда она видит только отключение.
+pub async fn assert_confirmation_fails(alice: &mut Peer, bob: &mut Peer) {
+    let mut confirmation_failed = false;
+    for (name, peer) in [("alice", alice), ("bob", bob)] {
+        loop {
+            let event = peer
+                .wait_for(&format!("{} disconnected", name), |e| {
+                    matches!(
+                        e,
+                        Event::Connected { .. }
+                            | Event::KeyConfirmationFailed { .. }
+                            | Event::Disconnected { .. }
+                    )
+                })
+                .await;
+            match event {
+                Event::Connected { .. } => panic!("{} connected with diverging keys", name),
+                Event::KeyConfirmationFailed { .. } => confirmation_failed = true,
+                _ => break,
+            }
+        }
+        assert!(peer.client.list_sessions().is_empty());
+    }
+    assert!(confirmation_failed);
+}
+
+/// Исчерпывает лимит сообщений Alice: сообщения ждут нового обмена ключами
+/// и доходят до Bob в исходном порядке уже новыми ключами
+pub async fn rekey_with_queued_messages(alice: &mut Peer, bob: &mut Peer, id: &str) {
+    // Лимит исчерпан только у этой сессии, глобальная настройка не меняется
+    let session = alice.client.session(id).unwrap();
+    session.crypto.lock().unwrap().as_mut().unwrap().sent = nonce_limit();
+
+    alice.client.send(id, "first").await.unwrap();
+    alice.client.send(id, "second").await.unwrap();
+    alice
+        .wait_for("nonce exhausted", |e| {
+            matches!(e, Event::NonceExhausted { .. })
+        })
+        .await;
+
+    assert_eq!(bob.wait_message().await, "first");
+    assert_eq!(bob.wait_message().await, "second");
+    assert!(session.outbox.lock().unwrap().is_empty());
+    assert_eq!(session.crypto.lock().unwrap().as_ref().unwrap().sent, 2);
+
+    alice.client.send(id, "third").await.unwrap();
+    assert_eq!(bob.wait_message().await, "third");
+}
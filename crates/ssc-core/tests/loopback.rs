@@ -0,0 +1,356 @@
+//! Два клиента в одном процессе: offer/answer с полным набором кандидатов,
+//! только host-кандидаты (без STUN/TURN), обмен ключами X25519 и чат поверх него.
+
+mod common;
+
+use bytes::Bytes;
+use common::{
+    assert_confirmation_fails, connect, host_candidates_only, rekey_with_queued_messages, Peer,
+};
+use ssc_core::events::Events;
+use ssc_core::peer::data_channel::{send_frame, MessageType, LEGACY_PUB_KEY_LEN};
+use ssc_core::peer::negotiation::KeyExchange;
+use ssc_core::peer::session::Session;
+use ssc_core::peer::signaling::{open_bundle, seal_bundle};
+use ssc_core::peer::traffic::{frame, framing_enabled, InnerKind};
+use ssc_core::peer::types::ConnectionBundle;
+use ssc_core::{Client, Event, SscError};
+use std::sync::{Arc, Weak};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handshake_gives_equal_sas_and_delivers_both_ways() {
+    let (mut alice, mut bob, id) = connect().await;
+
+    let alice_sas = alice.client.fingerprint(&id).unwrap();
+    let bob_sas = bob.client.fingerprint(&id).unwrap();
+    assert!(alice_sas.is_some());
+    assert_eq!(alice_sas, bob_sas);
+
+    for peer in [&alice, &bob] {
+        let session = peer.client.session(&id).unwrap();
+        let key_exchange = session.negotiated.lock().unwrap().map(|n| n.key_exchange);
+        assert_eq!(key_exchange, Some(KeyExchange::X25519));
+    }
+
+    alice.client.mark_verified(&id).unwrap();
+    bob.client.mark_verified(&id).unwrap();
+    assert!(alice.client.is_verified(&id).unwrap());
+    assert!(bob.client.is_verified(&id).unwrap());
+
+    alice.client.send(&id, "hello bob").await.unwrap();
+    assert_eq!(bob.wait_message().await, "hello bob");
+
+    bob.client.send(&id, "hello alice").await.unwrap();
+    assert_eq!(alice.wait_message().await, "hello alice");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replayed_frame_is_dropped() {
+    let (alice, mut bob, id) = connect().await;
+    let session = alice.client.session(&id).unwrap();
+
+    // Один и тот же зашифрованный кадр уходит дважды
+    let plaintext = if framing_enabled(&session) {
+        frame(InnerKind::Text, b"only once")
+    } else {
+        b"only once".to_vec()
+    };
+    let (_, _, ciphertext) = session
+        .crypto
+        .lock()
+        .unwrap()
+        .as_mut()
+        .unwrap()
+        .seal(&plaintext)
+        .unwrap();
+    assert!(send_frame(&session, MessageType::ChatText, ciphertext.clone()).await);
+    assert!(send_frame(&session, MessageType::ChatText, ciphertext).await);
+
+    assert_eq!(bob.wait_message().await, "only once");
+    assert_eq!(bob.message_within_quiet_period().await, None);
+
+    // Повтор отброшен, но сессия продолжает работать
+    alice.client.send(&id, "still here").await.unwrap();
+    assert_eq!(bob.wait_message().await, "still here");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bare_public_key_from_a_legacy_peer_is_reported() {
+    let (mut alice, bob, id) = connect().await;
+
+    // Старая версия шлёт свой X25519-ключ без конверта и Hello не ждёт
+    let session = bob.client.session(&id).unwrap();
+    let dc = session.data_ch.lock().unwrap().clone().unwrap();
+    dc.send(&Bytes::from(vec![7u8; LEGACY_PUB_KEY_LEN]))
+        .await
+        .unwrap();
+
+    match alice
+        .wait_for("alice incompatible peer", |e| {
+            matches!(e, Event::IncompatiblePeer { .. })
+        })
+        .await
+    {
+        Event::IncompatiblePeer { details, .. } => {
+            assert!(details.remote_versions.is_empty());
+            assert!(!details.local_versions.is_empty());
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn messages_sent_at_the_limit_are_queued_until_rekey() {
+    let (mut alice, mut bob, id) = connect().await;
+    alice.client.mark_verified(&id).unwrap();
+    bob.client.mark_verified(&id).unwrap();
+    rekey_with_queued_messages(&mut alice, &mut bob, &id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replayed_offer_and_answer_are_rejected() {
+    host_candidates_only();
+    let alice = Peer::new();
+    let bob = Peer::new();
+    let carol = Peer::new();
+
+    let offer = alice.client.create_offer(None, None).await.unwrap();
+    let answer = bob
+        .client
+        .accept_offer(&offer.encoded, None, None)
+        .await
+        .unwrap();
+
+    // Тот же offer второй раз не принимается никем
+    let replayed = carol.client.accept_offer(&offer.encoded, None, None).await;
+    assert!(matches!(replayed, Err(SscError::Replayed(_))));
+    assert!(carol.client.list_sessions().is_empty());
+
+    alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await
+        .unwrap();
+    let replayed = alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await;
+    assert!(matches!(replayed, Err(SscError::NoPendingOffer)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn answer_without_pake_is_rejected_on_every_attempt() {
+    host_candidates_only();
+    let mut alice = Peer::new();
+    let mut bob = Peer::new();
+    let passphrase = Some("correct horse battery staple".to_string());
+
+    let offer = alice
+        .client
+        .create_offer(passphrase.clone(), None)
+        .await
+        .unwrap();
+    let answer = bob
+        .client
+        .accept_offer(&offer.encoded, passphrase, None)
+        .await
+        .unwrap();
+
+    // Тот же answer без сообщения CPace, заново подписанный ключом Bob
+    let session = bob.client.session(&answer.session_id).unwrap();
+    let mut stripped: ConnectionBundle =
+        open_bundle(&answer.encoded, None, &session.events, None).unwrap();
+    stripped.pake = None;
+    let stripped = seal_bundle(&session, &stripped, None).unwrap();
+
+    for _ in 0..2 {
+        let result = alice
+            .client
+            .apply_answer(&offer.session_id, &stripped)
+            .await;
+        assert!(matches!(result, Err(SscError::Pake(_))));
+        assert!(!alice.client.is_connected(&offer.session_id).unwrap());
+    }
+
+    // Настоящий answer после отклонённых по-прежнему принимается
+    alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await
+        .unwrap();
+    alice
+        .wait_for("alice connected", |e| matches!(e, Event::Connected { .. }))
+        .await;
+    bob.wait_for("bob connected", |e| matches!(e, Event::Connected { .. }))
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resigned_bundles_give_different_keys_and_sas() {
+    host_candidates_only();
+    let mut alice = Peer::new();
+    let mut bob = Peer::new();
+
+    // Посредник не трогает SDP, а только переподписывает оба пакета своим ключом
+    let mallory = Session::new("mallory".into(), Events::new(), Weak::new());
+    let resign = |encoded: &str| {
+        let bundle: ConnectionBundle = open_bundle(encoded, None, &mallory.events, None).unwrap();
+        seal_bundle(&mallory, &bundle, None).unwrap()
+    };
+
+    let offer = alice.client.create_offer(None, None).await.unwrap();
+    let answer = bob
+        .client
+        .accept_offer(&resign(&offer.encoded), None, None)
+        .await
+        .unwrap();
+    alice
+        .client
+        .apply_answer(&offer.session_id, &resign(&answer.encoded))
+        .await
+        .unwrap();
+
+    // Ключи подписи входят в транскрипт: ключи и SAS сторон расходятся,
+    // и подтверждение ключей не проходит ни у одной стороны
+    assert_confirmation_fails(&mut alice, &mut bob).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_accept_does_not_burn_the_offer() {
+    host_candidates_only();
+    let alice = Peer::new();
+    let bob = Peer::new();
+    let passphrase = Some("correct horse battery staple".to_string());
+    let offer = alice
+        .client
+        .create_offer(passphrase.clone(), None)
+        .await
+        .unwrap();
+
+    // Пароль забыли ввести: offer отклонён, но остаётся непринятым
+    let forgotten = bob.client.accept_offer(&offer.encoded, None, None).await;
+    assert!(matches!(forgotten, Err(SscError::Pake(_))));
+    assert!(bob.client.list_sessions().is_empty());
+
+    bob.client
+        .accept_offer(&offer.encoded, passphrase, None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wrong_passphrase_fails_confirmation_and_closes_both_sides() {
+    host_candidates_only();
+    let mut alice = Peer::new();
+    let mut bob = Peer::new();
+
+    let offer = alice
+        .client
+        .create_offer(Some("correct horse".to_string()), None)
+        .await
+        .unwrap();
+    // CPace с другим паролем проходит, но ключи сторон расходятся
+    let answer = bob
+        .client
+        .accept_offer(&offer.encoded, Some("wrong horse".to_string()), None)
+        .await
+        .unwrap();
+    alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await
+        .unwrap();
+
+    assert_confirmation_fails(&mut alice, &mut bob).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn offer_with_an_existing_session_id_is_rejected() {
+    host_candidates_only();
+    let alice = Peer::new();
+    let offer = alice.client.create_offer(None, None).await.unwrap();
+
+    // id offer выбирает собеседник: здесь он совпадает с уже открытой сессией
+    let result = alice.client.accept_offer(&offer.encoded, None, None).await;
+    assert!(matches!(result, Err(SscError::SessionExists(_))));
+
+    // Прежняя сессия не заменена и по-прежнему ждёт answer
+    assert_eq!(alice.client.list_sessions().len(), 1);
+    let session = alice.client.session(&offer.session_id).unwrap();
+    assert!(session.pending_offer_id.lock().unwrap().is_some());
+    assert!(session.peer.lock().unwrap().is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn close_session_disconnects_both_sides() {
+    let (mut alice, mut bob, id) = connect().await;
+
+    alice.client.close_session(&id).await.unwrap();
+    alice
+        .wait_for("alice disconnected", |e| {
+            matches!(e, Event::Disconnected { .. })
+        })
+        .await;
+    bob.wait_for("bob disconnected", |e| {
+        matches!(e, Event::Disconnected { .. })
+    })
+    .await;
+
+    for peer in [&alice, &bob] {
+        assert!(peer.client.list_sessions().is_empty());
+        assert!(matches!(
+            peer.client.is_connected(&id),
+            Err(SscError::UnknownSession(_))
+        ));
+    }
+    assert!(matches!(
+        alice.client.send(&id, "too late").await,
+        Err(SscError::UnknownSession(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lost_peer_fails_the_session_after_grace_period() {
+    host_candidates_only();
+    let mut alice = Peer::new();
+    let bob = Arc::new(Client::new());
+
+    // Задачи соединения Bob живут в отдельном рантайме
+    let bob_runtime = tokio::runtime::Runtime::new().unwrap();
+    let offer = alice.client.create_offer(None, None).await.unwrap();
+    let answer = bob_runtime
+        .spawn({
+            let bob = bob.clone();
+            async move { bob.accept_offer(&offer.encoded, None, None).await }
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    alice
+        .client
+        .apply_answer(&offer.session_id, &answer.encoded)
+        .await
+        .unwrap();
+    alice
+        .wait_for("alice connected", |e| matches!(e, Event::Connected { .. }))
+        .await;
+
+    // Bob пропадает без закрытия: ни одного пакета больше не уходит
+    bob_runtime.shutdown_background();
+
+    alice
+        .wait_for("alice connection failed", |e| {
+            matches!(e, Event::ConnectionFailed { .. })
+        })
+        .await;
+    alice
+        .wait_for("alice disconnected", |e| {
+            matches!(e, Event::Disconnected { .. })
+        })
+        .await;
+    assert!(alice.client.list_sessions().is_empty());
+    assert!(matches!(
+        alice.client.is_connected(&offer.session_id),
+        Err(SscError::UnknownSession(_))
+    ));
+}
@@ -0,0 +1,209 @@
+//! Согласование режимов между двумя клиентами: Double Ratchet и гибридный
+//! X25519 + ML-KEM-768, у обеих сторон и только у одной.
+//!
+//! Настройки режимов глобальные, поэтому тесты этого файла идут по одному,
+//! а стороны получают разные настройки за счёт того, что Hello сессии
+//! фиксируется при её создании.
+
+mod common;
+
+use common::{connect_with, rekey_with_queued_messages, Peer};
+use ssc_core::peer::data_channel::{send_frame, MessageType};
+use ssc_core::peer::negotiation::{CipherSuite, KeyExchange};
+use ssc_core::peer::traffic::{frame, framing_enabled, InnerKind};
+use ssc_core::settings::{set_double_ratchet, set_pq_hybrid};
+use tokio::sync::Mutex;
+
+/// Тесты, меняющие глобальные настройки, не должны пересекаться
+static SETTINGS: Mutex<()> = Mutex::const_new(());
+
+/// Настройки Alice и Bob для одного соединения
+#[derive(Clone, Copy)]
+struct Modes {
+    double_ratchet: bool,
+    pq_hybrid: bool,
+}
+
+impl Modes {
+    fn apply(self) {
+        set_double_ratchet(self.double_ratchet);
+        set_pq_hybrid(self.pq_hybrid);
+    }
+}
+
+/// Настройки по умолчанию: только X25519 и симметричный храповик
+const PLAIN: Modes = Modes {
+    double_ratchet: false,
+    pq_hybrid: false,
+};
+
+/// Соединяет стороны с заданными настройками и проверяет согласованный режим
+async fn connect_modes(
+    alice_modes: Modes,
+    bob_modes: Modes,
+    suite: CipherSuite,
+    key_exchange: KeyExchange,
+) -> (Peer, Peer, String) {
+    alice_modes.apply();
+    let (alice, bob, id) = connect_with(|| bob_modes.apply()).await;
+    PLAIN.apply();
+
+    for peer in [&alice, &bob] {
+        let session = peer.client.session(&id).unwrap();
+        let negotiated = session.negotiated.lock().unwrap().unwrap();
+        assert_eq!(negotiated.suite, suite);
+        assert_eq!(negotiated.key_exchange, key_exchange);
+    }
+    (alice, bob, id)
+}
+
+/// Равные SAS, доставка в обе стороны и повторный обмен ключами
+async fn check_session(alice: &mut Peer, bob: &mut Peer, id: &str) {
+    let sas = alice.client.fingerprint(id).unwrap();
+    assert!(sas.is_some());
+    assert_eq!(sas, bob.client.fingerprint(id).unwrap());
+    alice.client.mark_verified(id).unwrap();
+    bob.client.mark_verified(id).unwrap();
+
+    for round in 0..3 {
+        alice.client.send(id, &format!("a{round}")).await.unwrap();
+        assert_eq!(bob.wait_message().await, format!("a{round}"));
+        bob.client.send(id, &format!("b{round}")).await.unwrap();
+        assert_eq!(alice.wait_message().await, format!("b{round}"));
+    }
+
+    rekey_with_queued_messages(alice, bob, id).await;
+    // SAS после повторного обмена не меняется, ответы идут новыми ключами
+    assert_eq!(alice.client.fingerprint(id).unwrap(), sas);
+    assert_eq!(bob.client.fingerprint(id).unwrap(), sas);
+    bob.client.send(id, "after rekey").await.unwrap();
+    assert_eq!(alice.wait_message().await, "after rekey");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn double_ratchet_on_both_sides() {
+    let _guard = SETTINGS.lock().await;
+    let modes = Modes {
+        double_ratchet: true,
+        ..PLAIN
+    };
+    let (mut alice, mut bob, id) = connect_modes(
+        modes,
+        modes,
+        CipherSuite::DoubleRatchet,
+        KeyExchange::X25519,
+    )
+    .await;
+    check_session(&mut alice, &mut bob, &id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn double_ratchet_reorders_frames_over_the_data_channel() {
+    let _guard = SETTINGS.lock().await;
+    let modes = Modes {
+        double_ratchet: true,
+        ..PLAIN
+    };
+    let (mut alice, mut bob, id) = connect_modes(
+        modes,
+        modes,
+        CipherSuite::DoubleRatchet,
+        KeyExchange::X25519,
+    )
+    .await;
+    alice.client.mark_verified(&id).unwrap();
+    bob.client.mark_verified(&id).unwrap();
+
+    // Bob отвечает первым, и Alice делает DH-шаг
+    bob.client.send(&id, "hello").await.unwrap();
+    assert_eq!(alice.wait_message().await, "hello");
+
+    // Кадры одной цепочки уходят в обратном порядке: Bob расшифровывает
+    // опережающие сообщения, а пропущенные - отложенными ключами
+    let session = alice.client.session(&id).unwrap();
+    let frames: Vec<_> = ["one", "two", "three"]
+        .iter()
+        .map(|text| {
+            let plaintext = if framing_enabled(&session) {
+                frame(InnerKind::Text, text.as_bytes())
+            } else {
+                text.as_bytes().to_vec()
+            };
+            let mut crypto = session.crypto.lock().unwrap();
+            crypto.as_mut().unwrap().seal(&plaintext).unwrap().2
+        })
+        .collect();
+    for ciphertext in frames.into_iter().rev() {
+        assert!(send_frame(&session, MessageType::ChatText, ciphertext).await);
+    }
+    for expected in ["three", "two", "one"] {
+        assert_eq!(bob.wait_message().await, expected);
+    }
+
+    // Следующий DH-шаг после отложенных ключей
+    bob.client.send(&id, "reply").await.unwrap();
+    assert_eq!(alice.wait_message().await, "reply");
+    alice.client.send(&id, "after").await.unwrap();
+    assert_eq!(bob.wait_message().await, "after");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pq_hybrid_on_both_sides() {
+    let _guard = SETTINGS.lock().await;
+    let modes = Modes {
+        pq_hybrid: true,
+        ..PLAIN
+    };
+    let (mut alice, mut bob, id) = connect_modes(
+        modes,
+        modes,
+        CipherSuite::SymmetricRatchet,
+        KeyExchange::X25519MlKem768,
+    )
+    .await;
+    check_session(&mut alice, &mut bob, &id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn double_ratchet_with_pq_hybrid() {
+    let _guard = SETTINGS.lock().await;
+    let modes = Modes {
+        double_ratchet: true,
+        pq_hybrid: true,
+    };
+    let (mut alice, mut bob, id) = connect_modes(
+        modes,
+        modes,
+        CipherSuite::DoubleRatchet,
+        KeyExchange::X25519MlKem768,
+    )
+    .await;
+    check_session(&mut alice, &mut bob, &id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn modes_enabled_on_one_side_fall_back() {
+    let _guard = SETTINGS.lock().await;
+    let all = Modes {
+        double_ratchet: true,
+        pq_hybrid: true,
+    };
+    let (mut alice, mut bob, id) = connect_modes(
+        all,
+        PLAIN,
+        CipherSuite::SymmetricRatchet,
+        KeyExchange::X25519,
+    )
+    .await;
+    check_session(&mut alice, &mut bob, &id).await;
+
+    // И в обратную сторону: режимы включены только у отвечающей стороны
+    let (mut alice, mut bob, id) = connect_modes(
+        PLAIN,
+        all,
+        CipherSuite::SymmetricRatchet,
+        KeyExchange::X25519,
+    )
+    .await;
+    check_session(&mut alice, &mut bob, &id).await;
+}
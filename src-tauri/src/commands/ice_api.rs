@@ -0,0 +1,32 @@
+use ssc_core::peer::types::{IceCandidate, ServerConfig};
+use ssc_core::peer::{connection, ice};
+use ssc_core::{Client, SscError};
+use tauri::{command, State};
+
+/// ICE кандидат от удаленной стороны для сессии `session_id`
+#[command]
+pub async fn add_ice_candidate(
+    client: State<'_, Client>,
+    session_id: String,
+    candidate: IceCandidate,
+) -> Result<(), SscError> {
+    client.add_ice_candidate(&session_id, candidate).await
+}
+
+/// Проверка доступности STUN/TURN сервера
+#[command]
+pub async fn check_ice_server_availability(config: ServerConfig) -> Result<bool, SscError> {
+    ice::check_ice_server_availability(config).await
+}
+
+/// Устанавливает пользовательские ICE серверы
+#[command]
+pub fn set_ice_servers(servers: Vec<ServerConfig>) -> Result<(), SscError> {
+    connection::set_ice_servers(servers)
+}
+
+/// Пользовательские ICE серверы или серверы по умолчанию
+#[command]
+pub fn get_ice_servers() -> Result<Vec<ServerConfig>, SscError> {
+    connection::get_ice_servers()
+}
@@ -0,0 +1,45 @@
+use ssc_core::peer::identity::{forget, load_pinned, local_fingerprint, PinnedContact};
+use ssc_core::{settings, Client, SscError};
+use tauri::{command, State};
+use tracing::info;
+
+/// Включает подпись эфемерного ключа долговременным ключом для следующих соединений
+#[command]
+pub fn set_identity_enabled(enabled: bool) -> Result<(), SscError> {
+    settings::set_identity_enabled(enabled)
+}
+
+/// Проверка, включены ли ключи идентичности
+#[command]
+pub fn get_identity_enabled() -> Result<bool, SscError> {
+    Ok(settings::identity_enabled())
+}
+
+/// Отпечаток нашего ключа идентичности
+#[command]
+pub fn get_identity_fingerprint() -> Result<Option<String>, SscError> {
+    Ok(local_fingerprint())
+}
+
+/// Отпечаток ключа идентичности собеседника в сессии `session_id`
+#[command]
+pub fn get_peer_identity(
+    client: State<'_, Client>,
+    session_id: String,
+) -> Result<Option<String>, SscError> {
+    client.peer_identity(&session_id)
+}
+
+/// Список закреплённых контактов
+#[command]
+pub fn list_pinned_contacts() -> Result<Vec<PinnedContact>, SscError> {
+    Ok(load_pinned())
+}
+
+/// Удаление контакта из закреплённых, `false` если такого контакта не было
+#[command]
+pub fn forget_pinned_contact(fingerprint: String) -> Result<bool, SscError> {
+    let removed = forget(&fingerprint);
+    info!("Forget pinned contact {}: {}", fingerprint, removed);
+    Ok(removed)
+}
@@ -0,0 +1,27 @@
+use crate::logger::{current_filter, export_recent, set_file_sink, set_filter};
+use ssc_core::SscError;
+use tauri::command;
+
+/// Смена фильтра уровней логирования во время работы (директивы `EnvFilter`)
+#[command]
+pub fn set_log_filter(filter: String) -> Result<(), SscError> {
+    set_filter(&filter)
+}
+
+/// Текущий фильтр уровней логирования
+#[command]
+pub fn get_log_filter() -> Result<Option<String>, SscError> {
+    Ok(current_filter())
+}
+
+/// Включение записи лога в файлы в каталоге данных приложения
+#[command]
+pub fn set_log_file(enabled: bool) -> Result<(), SscError> {
+    set_file_sink(enabled)
+}
+
+/// Экспорт последних строк лога (без секретов) для отчёта об ошибке, возвращает путь к файлу
+#[command]
+pub fn export_logs() -> Result<String, SscError> {
+    export_recent().map(|path| path.display().to_string())
+}
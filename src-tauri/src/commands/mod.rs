@@ -1,3 +1,7 @@
 pub mod candidate_api;
+pub mod ice_api;
+pub mod identity_api;
 pub mod legacy_api;
+pub mod log_api;
+pub mod session_api;
 pub mod util_api;
@@ -0,0 +1,21 @@
+use ssc_core::peer::session::SessionInfo;
+use ssc_core::{Client, SscError};
+use tauri::{command, State};
+
+/// Список разговоров с их состоянием
+#[command]
+pub fn list_sessions(client: State<'_, Client>) -> Result<Vec<SessionInfo>, SscError> {
+    Ok(client.list_sessions())
+}
+
+/// Переключение UI на другой разговор
+#[command]
+pub fn switch_session(client: State<'_, Client>, session_id: String) -> Result<(), SscError> {
+    client.switch_session(&session_id)
+}
+
+/// Закрытие одного разговора, остальные продолжают работать
+#[command]
+pub async fn close_session(client: State<'_, Client>, session_id: String) -> Result<(), SscError> {
+    client.close_session(&session_id).await
+}
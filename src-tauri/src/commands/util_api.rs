@@ -1,116 +1,169 @@
-use crate::logger::emit_disconnected;
-use crate::logger::log;
-use crate::peer::crypto::u64_to_nonce;
-use crate::peer::state::{
-    COLLECTING_CANDIDATES, CRYPTO, DATA_CH, DISCONNECT_TASK, LOCAL_CANDIDATES, MY_PRIV, MY_PUB,
-    PEER, PENDING_REMOTE_CANDIDATES, WAS_CONNECTED,
-};
-use bytes::Bytes;
-use chacha20poly1305::aead::Aead;
-use tauri::command;
-
-/// текст по каналу
-#[command]
-pub async fn send_text(text: String) -> bool {
-    log(&format!("send_text called with: {}", text));
-    let dc = { DATA_CH.lock().unwrap().as_ref().cloned() };
-    if let Some(dc) = dc {
-        // Получаем данные из мьютекса и освобождаем его
-        let result = {
-            let mut crypto_guard = CRYPTO.lock().unwrap();
-            if let Some(ref mut ctx) = *crypto_guard {
-                let seq_num = ctx.send_n;
-                let nonce = u64_to_nonce(seq_num);
-                ctx.send_n += 1;
-
-                let plaintext = text.into_bytes();
-                match ctx.sealing.encrypt(&nonce, plaintext.as_ref()) {
-                    Ok(ciphertext) => {
-                        log(&format!(
-                            "Encrypted message with seq {}, length: {}",
-                            seq_num,
-                            ciphertext.len()
-                        ));
-                        Some(ciphertext)
-                    }
-                    Err(_) => {
-                        log("Encryption failed");
-                        None
-                    }
-                }
-            } else {
-                log("No crypto context available for sending");
-                None
-            }
-        }; // мьютекс освобождается здесь
-
-        if let Some(ciphertext) = result {
-            let send_result = dc.send(&Bytes::from(ciphertext)).await.is_ok();
-            log(&format!("Send result: {}", send_result));
-            return send_result;
-        }
-    }
-    log("No data channel available for sending");
-    false
+use ssc_core::peer::sas::SasFormat;
+use ssc_core::peer::traffic::PaddingPolicy;
+use ssc_core::peer::types::SendStatus;
+use ssc_core::{settings, Client, SscError};
+use tauri::{command, State};
+
+/// текст по каналу сессии `session_id`: отправлен или ждёт новых ключей
+#[command]
+pub async fn send_text(
+    client: State<'_, Client>,
+    session_id: String,
+    text: String,
+) -> Result<SendStatus, SscError> {
+    client.send(&session_id, &text).await
 }
 
 /// получение fingerprint
 #[command]
-pub fn get_fingerprint() -> Option<String> {
-    let crypto_guard = CRYPTO.lock().unwrap();
-    let result = crypto_guard.as_ref().map(|c| {
-        log(&format!("Found crypto context with SAS: {}", c.sas));
-        c.sas.clone()
-    });
-    log(&format!(
-        "get_fingerprint called, crypto exists: {}, result: {:?}",
-        crypto_guard.is_some(),
-        result
-    ));
-    result
+pub fn get_fingerprint(
+    client: State<'_, Client>,
+    session_id: String,
+) -> Result<Option<String>, SscError> {
+    client.fingerprint(&session_id)
+}
+
+/// получение fingerprint в нужном формате: hex, words, emoji или numeric
+#[command]
+pub fn get_fingerprint_as(
+    client: State<'_, Client>,
+    session_id: String,
+    format: SasFormat,
+) -> Result<Option<String>, SscError> {
+    client.fingerprint_as(&session_id, format)
+}
+
+/// пользователь подтвердил, что SAS совпадает на обоих устройствах
+#[command]
+pub fn mark_fingerprint_verified(
+    client: State<'_, Client>,
+    session_id: String,
+) -> Result<(), SscError> {
+    client.mark_verified(&session_id)
+}
+
+/// пользователь отверг SAS: разрываем соединение и зануляем ключи этой сессии
+#[command]
+pub async fn reject_fingerprint(
+    client: State<'_, Client>,
+    session_id: String,
+) -> Result<(), SscError> {
+    client.reject(&session_id).await
+}
+
+/// проверка подтверждения SAS в сессии
+#[command]
+pub fn is_fingerprint_verified(
+    client: State<'_, Client>,
+    session_id: String,
+) -> Result<bool, SscError> {
+    client.is_verified(&session_id)
 }
 
 /// проверка готовности соединения
 #[command]
-pub fn is_connected() -> bool {
-    CRYPTO.lock().unwrap().is_some()
+pub fn is_connected(client: State<'_, Client>, session_id: String) -> Result<bool, SscError> {
+    client.is_connected(&session_id)
 }
 
-/// ручное разъединение
+/// Требовать подтверждения SAS перед отправкой сообщений
 #[command]
-pub async fn disconnect() {
-    // извлекаем data channel и освобождаем мьютекс
-    let dc = DATA_CH.lock().unwrap().take();
-    if let Some(dc) = dc {
-        let _ = dc.close().await;
-    }
+pub fn set_require_verification(enabled: bool) -> Result<(), SscError> {
+    settings::set_require_verification(enabled);
+    Ok(())
+}
 
-    // извлекаем peer connection и освобождаем мьютекс
-    let pc = PEER.lock().unwrap().take();
-    if let Some(pc) = pc {
-        let _ = pc.close().await;
-    }
+/// Проверка, требуется ли подтверждение SAS
+#[command]
+pub fn get_require_verification() -> Result<bool, SscError> {
+    Ok(settings::require_verification())
+}
 
-    // отменяем отложенный disconnect, если он был
-    if let Some(handle) = DISCONNECT_TASK.lock().unwrap().take() {
-        log("Aborting pending disconnect task in manual disconnect");
-        handle.abort();
-    }
+/// Предлагать Double Ratchet в Hello следующих соединений (используется, если его предложат обе стороны)
+#[command]
+pub fn set_double_ratchet(enabled: bool) -> Result<(), SscError> {
+    settings::set_double_ratchet(enabled);
+    Ok(())
+}
 
-    // очищаем криптографический контекст и ключи
-    log("Clearing CRYPTO context in disconnect");
-    *CRYPTO.lock().unwrap() = None;
-    *MY_PRIV.lock().unwrap() = None;
-    *MY_PUB.lock().unwrap() = None;
-    *WAS_CONNECTED.lock().unwrap() = false;
+/// Проверка, включён ли Double Ratchet
+#[command]
+pub fn get_double_ratchet() -> Result<bool, SscError> {
+    Ok(settings::double_ratchet())
+}
 
-    // очищаем отложенные кандидаты
-    PENDING_REMOTE_CANDIDATES.lock().unwrap().clear();
+/// Предлагать гибридный обмен X25519 + ML-KEM-768 в Hello следующих соединений
+#[command]
+pub fn set_pq_hybrid(enabled: bool) -> Result<(), SscError> {
+    settings::set_pq_hybrid(enabled);
+    Ok(())
+}
 
-    // очищаем локальные кандидаты
-    LOCAL_CANDIDATES.lock().unwrap().clear();
-    *COLLECTING_CANDIDATES.lock().unwrap() = false;
+/// Проверка, включён ли гибридный обмен ключами
+#[command]
+pub fn get_pq_hybrid() -> Result<bool, SscError> {
+    Ok(settings::pq_hybrid())
+}
 
-    // отправляем событие отключения
-    emit_disconnected();
+/// Печатать ли в лог тексты сообщений, ключи и учётные данные
+#[command]
+pub fn set_unsafe_diagnostics(enabled: bool) -> Result<(), SscError> {
+    settings::set_unsafe_diagnostics(enabled);
+    Ok(())
+}
+
+/// Проверка, включены ли unsafe diagnostics
+#[command]
+pub fn get_unsafe_diagnostics() -> Result<bool, SscError> {
+    Ok(settings::get_unsafe_diagnostics())
+}
+
+/// Устанавливает политику выравнивания длины сообщений
+#[command]
+pub fn set_padding_policy(policy: PaddingPolicy) -> Result<(), SscError> {
+    settings::set_padding_policy(policy)
+}
+
+/// Текущая политика выравнивания длины сообщений
+#[command]
+pub fn get_padding_policy() -> Result<PaddingPolicy, SscError> {
+    Ok(settings::padding_policy())
+}
+
+/// Средний интервал холостых сообщений в миллисекундах, 0 выключает их
+///
+/// Применяется сразу ко всем установленным сессиям.
+#[command]
+pub fn set_cover_traffic(client: State<'_, Client>, interval_ms: u64) -> Result<(), SscError> {
+    client.set_cover_traffic(interval_ms)
+}
+
+/// Интервал холостых сообщений в миллисекундах (0 - выключены)
+#[command]
+pub fn get_cover_traffic() -> Result<u64, SscError> {
+    Ok(settings::cover_traffic())
+}
+
+/// Устанавливает порог сообщений на один обмен ключами
+#[command]
+pub fn set_nonce_limit(limit: u64) -> Result<(), SscError> {
+    settings::set_nonce_limit(limit)
+}
+
+/// Устанавливает время жизни offer/answer в секундах
+#[command]
+pub fn set_offer_ttl(secs: i64) -> Result<(), SscError> {
+    settings::set_offer_ttl(secs)
+}
+
+/// Текущее время жизни offer/answer в секундах
+#[command]
+pub fn get_offer_ttl() -> Result<i64, SscError> {
+    Ok(settings::offer_ttl())
+}
+
+/// Текущий порог сообщений на один обмен ключами
+#[command]
+pub fn get_nonce_limit() -> Result<u64, SscError> {
+    Ok(settings::nonce_limit())
 }
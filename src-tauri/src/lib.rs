@@ -1,8 +1,12 @@
 mod commands;
 mod config;
 mod logger;
-mod peer;
-mod utils;
+mod state;
+
+use ssc_core::Client;
+use tauri::{Emitter, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -11,26 +15,90 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logger::init_logging();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            match app.path().app_data_dir() {
+                Ok(dir) => ssc_core::settings::set_data_dir(dir),
+                Err(e) => warn!("App data directory unavailable: {}", e),
+            }
+
+            // События сессий уходят в webview под прежними именами `ssc-*`
+            let client = Client::new();
+            let mut events = client.subscribe();
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            if let Err(e) = handle.emit(event.name(), event.payload()) {
+                                warn!("Failed to emit {}: {}", event.name(), e);
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("UI lagged behind, {} events dropped", skipped)
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+                info!("Event forwarding stopped");
+            });
+            app.manage(client);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Legacy API
             commands::legacy_api::generate_offer,
             commands::legacy_api::accept_offer_and_create_answer,
             commands::legacy_api::set_answer,
             // New API with candidates
+            commands::candidate_api::generate_transport_code,
             commands::candidate_api::generate_offer_with_candidates,
             commands::candidate_api::accept_offer_with_candidates,
             commands::candidate_api::set_answer_with_candidates,
-            peer::ice::add_ice_candidate,
+            commands::ice_api::add_ice_candidate,
             // Utility functions
             commands::util_api::send_text,
             commands::util_api::get_fingerprint,
+            commands::util_api::get_fingerprint_as,
+            commands::util_api::mark_fingerprint_verified,
+            commands::util_api::reject_fingerprint,
+            commands::util_api::is_fingerprint_verified,
             commands::util_api::is_connected,
-            commands::util_api::disconnect,
-            peer::ice::check_ice_server_availability,
-            peer::connection::set_ice_servers,
-            peer::connection::get_ice_servers,
+            commands::session_api::list_sessions,
+            commands::session_api::switch_session,
+            commands::session_api::close_session,
+            commands::util_api::set_double_ratchet,
+            commands::util_api::get_double_ratchet,
+            commands::util_api::set_pq_hybrid,
+            commands::util_api::get_pq_hybrid,
+            commands::util_api::set_unsafe_diagnostics,
+            commands::util_api::get_unsafe_diagnostics,
+            commands::util_api::set_padding_policy,
+            commands::util_api::get_padding_policy,
+            commands::util_api::set_cover_traffic,
+            commands::util_api::get_cover_traffic,
+            commands::util_api::set_nonce_limit,
+            commands::util_api::get_nonce_limit,
+            commands::util_api::set_offer_ttl,
+            commands::util_api::get_offer_ttl,
+            commands::util_api::set_require_verification,
+            commands::util_api::get_require_verification,
+            commands::identity_api::set_identity_enabled,
+            commands::identity_api::get_identity_enabled,
+            commands::identity_api::get_identity_fingerprint,
+            commands::identity_api::get_peer_identity,
+            commands::identity_api::list_pinned_contacts,
+            commands::identity_api::forget_pinned_contact,
+            commands::log_api::set_log_filter,
+            commands::log_api::get_log_filter,
+            commands::log_api::set_log_file,
+            commands::log_api::export_logs,
+            commands::ice_api::check_ice_server_availability,
+            commands::ice_api::set_ice_servers,
+            commands::ice_api::get_ice_servers,
             greet
         ])
         .run(tauri::generate_context!())
@@ -1,120 +1,171 @@
-use crate::peer::state::{
-    APP, COLLECTING_CANDIDATES, CRYPTO, LOCAL_CANDIDATES, MY_PRIV, MY_PUB,
-    PENDING_REMOTE_CANDIDATES, WAS_CONNECTED,
-};
-use tauri::Emitter;
-use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
-use webrtc::peer_connection::RTCPeerConnection;
+use crate::config::DEFAULT_LOG_FILTER;
+use crate::state::{LOG_FILE, LOG_FILTER, MAX_LOG_FILES, MAX_RECENT_LOG_LINES, RECENT_LOGS};
+use ssc_core::logger::unsafe_diagnostics;
+use ssc_core::settings::data_dir;
+use ssc_core::SscError;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tracing::{info, warn};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
-/// Логирование с временными метками
-pub fn log(msg: &str) {
-    // Проверяем конфигурацию логирования
-    if crate::config::LOGGING_ENABLED {
-        #[cfg(debug_assertions)]
-        {
-            // В режиме разработки дополнительно проверяем dev::ENABLE_LOGGING
-            if !crate::config::dev::ENABLE_LOGGING {
-                return;
-            }
-        }
+/// Handle для смены фильтра уровней во время работы
+pub type FilterHandle = reload::Handle<EnvFilter, Registry>;
 
-        let now = chrono::Local::now();
-        println!("RUST: [{}] {}", now.format("%Y-%m-%d %H:%M:%S%.3f"), msg);
-    }
+/// Строка лога для экспорта
+pub struct RecentLine {
+    pub line: String,
+    /// Записана при включённых unsafe diagnostics и может содержать секреты
+    pub unsafe_diagnostics: bool,
 }
 
-/// Печать ICE-candidate при появлении (Trickle-ICE)
-pub async fn dump_candidate(label: &str, cand: &RTCIceCandidate) {
-    if let Ok(init) = cand.to_json() {
-        log(&format!(
-            "Trickle {label}: candidate={} sdp_mid={:?} sdp_mline_index={:?} username_fragment={:?}",
-            init.candidate, init.sdp_mid, init.sdp_mline_index, init.username_fragment
-        ));
-    }
-}
+/// Инициализация логирования: stdout, буфер последних строк для экспорта и необязательный файл
+///
+/// Фильтр задаётся директивами `EnvFilter` (`RUST_LOG` имеет приоритет над значением по умолчанию)
+/// и меняется во время работы через `set_log_filter`.
+pub fn init_logging() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
 
-/// Быстрый снимок getStats → выбранная пара
-pub async fn dump_selected_pair(pc: &RTCPeerConnection, moment: &str) {
-    let stats = pc.get_stats().await;
-    for (_, v) in stats.reports {
-        match v {
-            webrtc::stats::StatsReportType::CandidatePair(pair) => {
-                if pair.nominated {
-                    log(&format!(
-                        "STATS {moment}: {}:{}  type: {:?}  bytes={}/{} state={:?}",
-                        pair.local_candidate_id,
-                        pair.remote_candidate_id,
-                        pair.stats_type,
-                        pair.bytes_sent,
-                        pair.bytes_received,
-                        pair.state
-                    ));
-                }
-            }
-            _ => {}
-        }
-    }
-}
+    let initialized = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| SinkWriter),
+        )
+        .try_init();
 
-fn emit_state(evt: &str) {
-    log(&format!("emit_state called with event: {}", evt));
-    if let Some(app) = APP.lock().unwrap().clone() {
-        log(&format!("APP handle exists, emitting event: {}", evt));
-        match app.emit(evt, ()) {
-            Ok(_) => log(&format!("Successfully emitted event: {}", evt)),
-            Err(e) => log(&format!("Failed to emit event {}: {:?}", evt, e)),
-        }
-    } else {
-        log(&format!("APP handle is None, cannot emit event: {}", evt));
+    if initialized.is_ok() {
+        *LOG_FILTER.lock().unwrap() = Some(handle);
     }
 }
 
-pub fn emit_connected() {
-    log("emit_connected called - setting WAS_CONNECTED flag");
-    *WAS_CONNECTED.lock().unwrap() = true;
-    emit_state("ssc-connected");
+/// Смена фильтра уровней, например `info,ssc_core::peer::ice=debug`
+pub fn set_filter(directives: &str) -> Result<(), SscError> {
+    let filter = EnvFilter::try_new(directives).map_err(|e| {
+        warn!("Rejected log filter {:?}", directives);
+        SscError::InvalidArgument(e.to_string())
+    })?;
+    let handle = LOG_FILTER
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| SscError::InvalidArgument("logging is not initialized".into()))?;
+    handle
+        .reload(filter)
+        .map_err(|e| SscError::InvalidArgument(e.to_string()))?;
+    info!("Log filter set to {}", directives);
+    Ok(())
 }
 
-pub fn emit_disconnected() {
-    log("emit_disconnected called - clearing all state");
-    log("Clearing CRYPTO context in emit_disconnected");
-    *CRYPTO.lock().unwrap() = None;
-    *MY_PRIV.lock().unwrap() = None;
-    *MY_PUB.lock().unwrap() = None;
-    *WAS_CONNECTED.lock().unwrap() = false;
-
-    // очищаем отложенные кандидаты
-    PENDING_REMOTE_CANDIDATES.lock().unwrap().clear();
+/// Текущий фильтр уровней
+pub fn current_filter() -> Option<String> {
+    let handle = LOG_FILTER.lock().unwrap().clone()?;
+    handle.with_current(|filter| filter.to_string()).ok()
+}
 
-    // очищаем локальные кандидаты
-    LOCAL_CANDIDATES.lock().unwrap().clear();
-    *COLLECTING_CANDIDATES.lock().unwrap() = false;
+/// Включает или выключает запись лога в файлы `logs/ssc.*.log` (ротация раз в сутки)
+pub fn set_file_sink(enabled: bool) -> Result<(), SscError> {
+    if !enabled {
+        *LOG_FILE.lock().unwrap() = None;
+        info!("Log file sink disabled");
+        return Ok(());
+    }
 
-    emit_state("ssc-disconnected");
+    let dir = data_dir()
+        .inspect_err(|e| warn!("Cannot enable log file sink: {}", e))?
+        .join("logs");
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("ssc")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| {
+            warn!("Failed to open log file sink: {}", e);
+            SscError::Storage(e.to_string())
+        })?;
+    *LOG_FILE.lock().unwrap() = Some(appender);
+    info!("Log file sink enabled in {}", dir.display());
+    Ok(())
 }
 
-pub fn emit_message(msg: &str) {
-    if let Some(app) = APP.lock().unwrap().clone() {
-        let _ = app.emit("ssc-message", msg);
+/// Сохраняет последние строки лога в файл для отчёта об ошибке, возвращает путь
+///
+/// Строки, записанные при включённых unsafe diagnostics, в отчёт не попадают.
+pub fn export_recent() -> Result<PathBuf, SscError> {
+    let mut report = format!(
+        "ssc {} ({} {})\nlog filter: {}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        current_filter().unwrap_or_default()
+    );
+    let mut omitted = 0;
+    for entry in RECENT_LOGS.lock().unwrap().iter() {
+        if entry.unsafe_diagnostics {
+            omitted += 1;
+            continue;
+        }
+        report.push_str(&entry.line);
+        report.push('\n');
+    }
+    if omitted > 0 {
+        report.push_str(&format!(
+            "[{} lines written with unsafe diagnostics omitted]\n",
+            omitted
+        ));
     }
-}
 
-pub fn emit_connection_problem() {
-    log("emit_connection_problem called - connection issues detected");
-    emit_state("ssc-connection-problem");
+    let path = data_dir()?.join(format!(
+        "ssc-logs-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::write(&path, report).map_err(|e| {
+        warn!("Failed to export logs: {}", e);
+        SscError::Storage(e.to_string())
+    })?;
+    info!("Exported logs to {}", path.display());
+    Ok(path)
 }
 
-pub fn emit_connection_recovering() {
-    log("emit_connection_recovering called - connection is recovering");
-    emit_state("ssc-connection-recovering");
-}
+/// Приёмник отформатированных строк: буфер для экспорта и, если включён, файл
+///
+/// При включённых unsafe diagnostics строки в файл не пишутся: в них могут быть
+/// текст сообщений и ключи, а файл переживает сессию и может попасть в отчёт.
+struct SinkWriter;
 
-pub fn emit_connection_recovered() {
-    log("emit_connection_recovered called - connection recovered");
-    emit_state("ssc-connection-recovered");
-}
+impl Write for SinkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let unsafe_line = unsafe_diagnostics();
+        {
+            let mut recent = RECENT_LOGS.lock().unwrap();
+            if recent.len() >= MAX_RECENT_LOG_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(RecentLine {
+                line: String::from_utf8_lossy(buf).trim_end().to_string(),
+                unsafe_diagnostics: unsafe_line,
+            });
+        }
+        if unsafe_line {
+            return Ok(buf.len());
+        }
+        if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+            file.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
 
-pub fn emit_connection_failed() {
-    log("emit_connection_failed called - connection recovery failed");
-    emit_state("ssc-connection-failed");
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
 }
@@ -1,6 +0,0 @@
-pub mod connection;
-pub mod crypto;
-pub mod data_channel;
-pub mod ice;
-pub mod state;
-pub mod types;
@@ -0,0 +1,27 @@
+use crate::logger::{FilterHandle, RecentLine};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tracing_appender::rolling::RollingFileAppender;
+
+// ========== GLOBAL STATE ==========
+//
+// Состояние оболочки приложения; протокол и его настройки - в `ssc_core`.
+
+/// Handle фильтра уровней логирования (после `init_logging`)
+pub static LOG_FILTER: Lazy<Mutex<Option<FilterHandle>>> = Lazy::new(|| Mutex::new(None));
+
+/// Файловый приёмник лога, если включён
+pub static LOG_FILE: Lazy<Mutex<Option<RollingFileAppender>>> = Lazy::new(|| Mutex::new(None));
+
+/// Последние строки лога для экспорта в отчёт об ошибке
+pub static RECENT_LOGS: Lazy<Mutex<VecDeque<RecentLine>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+// ========== CONSTANTS ==========
+
+/// Сколько последних строк лога хранить для экспорта
+pub const MAX_RECENT_LOG_LINES: usize = 2000;
+
+/// Сколько суточных файлов лога хранить
+pub const MAX_LOG_FILES: usize = 7;
@@ -0,0 +1,115 @@
+import {useEffect, useState} from 'react';
+import type {FC} from 'react';
+import {invoke} from '@tauri-apps/api/core';
+import {Plus, ShieldAlert, X} from 'lucide-react';
+import type {IdentityState, SessionInfo, SessionState} from '@/lib/session';
+
+interface Props {
+  activeId: string;
+  chatIds: string[];
+  onSwitch: (id: string) => void;
+  onClose: (id: string) => void;
+  onNew: () => void;
+}
+
+const STATE_LABELS: Record<SessionState, string> = {
+  gathering: 'Сбор кандидатов',
+  connecting: 'Подключение',
+  awaiting_verification: 'Сверка отпечатков',
+  connected: 'Подключено',
+  recovering: 'Восстановление',
+};
+
+const STATE_COLORS: Record<SessionState, string> = {
+  gathering: 'bg-slate-400',
+  connecting: 'bg-yellow-500',
+  awaiting_verification: 'bg-blue-500',
+  connected: 'bg-emerald-500',
+  recovering: 'bg-orange-500',
+};
+
+// Незакреплённый или сменившийся ключ идентичности: собеседник не проверен
+const IDENTITY_WARNINGS: Partial<Record<IdentityState, string>> = {
+  unknown: 'Новый контакт: ключ идентичности ещё не подтверждён',
+  changed: 'Ключ идентичности контакта изменился',
+};
+
+/**
+ * Tabs for all live sessions: switch between chats, close one, open a new one.
+ * Only sessions that already reached the chat screen can be switched to.
+ */
+export const SessionTabs: FC<Props> = ({activeId, chatIds, onSwitch, onClose, onNew}) => {
+  const [sessions, setSessions] = useState<SessionInfo[]>([]);
+
+  // Состояние сессий меняется в Rust, поэтому просто опрашиваем список
+  useEffect(() => {
+    let cancelled = false;
+    const refresh = async () => {
+      try {
+        const list = await invoke<SessionInfo[]>('list_sessions');
+        if (!cancelled) setSessions(list);
+      } catch (e) {
+        console.error('list_sessions error', e);
+      }
+    };
+    refresh();
+    const timer = setInterval(refresh, 2000);
+    return () => {
+      cancelled = true;
+      clearInterval(timer);
+    };
+  }, [chatIds]);
+
+  return (
+    <div className="flex-shrink-0 flex items-center gap-2 overflow-x-auto px-3 py-2 bg-slate-900/60 border-b border-slate-700">
+      {sessions.map((s, index) => {
+        const isChat = chatIds.includes(s.session_id);
+        const isActive = s.session_id === activeId;
+        return (
+          <div
+            key={s.session_id}
+            className={`flex items-center gap-2 rounded-full border px-3 py-1 text-xs whitespace-nowrap ${
+              isActive ? 'border-emerald-500 text-white' : 'border-slate-600 text-slate-300'
+            }`}
+          >
+            <button
+              type="button"
+              disabled={!isChat || isActive}
+              onClick={() => onSwitch(s.session_id)}
+              className="flex items-center gap-2 disabled:cursor-default"
+              title={STATE_LABELS[s.state]}
+            >
+              <span className={`w-2 h-2 rounded-full ${STATE_COLORS[s.state]}`} />
+              <span>Чат {index + 1}</span>
+              <span className="text-slate-500">{STATE_LABELS[s.state]}</span>
+              {s.identity && IDENTITY_WARNINGS[s.identity] && (
+                <span
+                  title={IDENTITY_WARNINGS[s.identity]}
+                  className={s.identity === 'changed' ? 'text-red-400' : 'text-yellow-400'}
+                >
+                  <ShieldAlert className="w-3 h-3" />
+                </span>
+              )}
+            </button>
+            <button
+              type="button"
+              onClick={() => onClose(s.session_id)}
+              className="text-slate-500 hover:text-red-400"
+              title="Закрыть"
+            >
+              <X className="w-3 h-3" />
+            </button>
+          </div>
+        );
+      })}
+      <button
+        type="button"
+        onClick={onNew}
+        className="flex items-center gap-1 rounded-full border border-dashed border-slate-600 px-3 py-1 text-xs text-slate-400 hover:text-white"
+      >
+        <Plus className="w-3 h-3" />
+        Новый чат
+      </button>
+    </div>
+  );
+};
@@ -0,0 +1,52 @@
+// Результат generate/accept: пакет для собеседника и id сессии
+export interface SignalingBundle {
+  session_id: string;
+  encoded: string;
+}
+
+// Каждое событие ssc-* помечено сессией; null - сессия ещё не известна
+export function isForSession(payload: unknown, sessionId: string | null): boolean {
+  const id = (payload as { session_id?: string | null } | null)?.session_id;
+  return id == null || id === sessionId;
+}
+
+export type SessionState =
+  | 'gathering'
+  | 'connecting'
+  | 'awaiting_verification'
+  | 'connected'
+  | 'recovering';
+
+// Формат SAS для get_fingerprint_as
+export type SasFormat = 'hex' | 'words' | 'emoji' | 'numeric';
+
+// Результат send_text: отправлено или ждёт нового обмена ключами
+export type SendStatus = 'sent' | 'queued';
+
+// Сверка ключа идентичности собеседника с закреплёнными контактами
+export type IdentityState = 'unknown' | 'verified' | 'changed';
+
+// Строка списка сессий из list_sessions
+export interface SessionInfo {
+  session_id: string;
+  state: SessionState;
+  active: boolean;
+  created_at: number;
+  // null - собеседник не подписал ключ или ключи ещё не согласованы
+  identity: IdentityState | null;
+}
+
+// Payload ssc-incompatible-peer; пустые remote_versions - собеседник старой версии
+export interface IncompatiblePeer {
+  session_id: string;
+  local_versions: number[];
+  remote_versions: number[];
+  local_suites: string[];
+  remote_suites: string[];
+}
+
+export function describeIncompatible(payload: IncompatiblePeer): string {
+  return payload.remote_versions.length === 0
+    ? 'У собеседника старая версия приложения, её нужно обновить'
+    : 'Нет общей версии протокола с собеседником';
+}
@@ -0,0 +1,80 @@
+// Ошибка любой команды из Rust: { kind, detail }
+export type SscError =
+  | { kind: 'decode'; detail: string }
+  | { kind: 'decompression_limit'; detail: { limit: number } }
+  | { kind: 'bad_signature' }
+  | { kind: 'bad_mac' }
+  | { kind: 'identity_changed'; detail: string }
+  | { kind: 'transport_code_required' }
+  | { kind: 'bad_transport_code' }
+  | { kind: 'not_encrypted' }
+  | { kind: 'pake'; detail: string }
+  | { kind: 'expired'; detail: { age_secs: number; ttl_secs: number } }
+  | { kind: 'from_future'; detail: { ahead_secs: number } }
+  | { kind: 'id_mismatch'; detail: { expected: string; actual: string } }
+  | { kind: 'no_pending_offer' }
+  | { kind: 'replayed'; detail: string }
+  | { kind: 'invalid_sdp'; detail: string }
+  | { kind: 'no_peer' }
+  | { kind: 'no_data_channel' }
+  | { kind: 'no_session' }
+  | { kind: 'unknown_session'; detail: string }
+  | { kind: 'session_exists'; detail: string }
+  | { kind: 'not_verified' }
+  | { kind: 'nonce_exhausted'; detail: { sent: number } }
+  | { kind: 'crypto'; detail: string }
+  | { kind: 'timeout'; detail: string }
+  | { kind: 'web_rtc'; detail: string }
+  | { kind: 'invalid_argument'; detail: string }
+  | { kind: 'storage'; detail: string };
+
+export function describeError(error: unknown, fallback: string): string {
+  const e = error as SscError | undefined;
+  switch (e?.kind) {
+    case 'decode':
+    case 'bad_signature':
+    case 'bad_mac':
+      return 'Данные повреждены или подделаны';
+    case 'decompression_limit':
+      return 'Данные слишком большие';
+    case 'identity_changed':
+      return 'Ключ собеседника изменился';
+    case 'transport_code_required':
+      return 'Данные зашифрованы, нужен код переноса';
+    case 'bad_transport_code':
+      return 'Неверный код переноса';
+    case 'not_encrypted':
+      return 'Данные не зашифрованы, код переноса не нужен';
+    case 'pake':
+      return 'Неверный пароль или он не указан';
+    case 'expired':
+      return 'Срок действия кода истёк, сгенерируйте новый';
+    case 'from_future':
+      return 'Время на устройствах сильно расходится';
+    case 'id_mismatch':
+      return 'Ответ относится к другому приглашению';
+    case 'no_pending_offer':
+      return 'Нет ожидающего приглашения';
+    case 'replayed':
+      return 'Это приглашение уже было использовано';
+    case 'invalid_sdp':
+      return 'Некорректное описание соединения';
+    case 'no_peer':
+    case 'no_data_channel':
+      return 'Нет соединения с собеседником';
+    case 'no_session':
+      return 'Защищённый канал ещё не установлен';
+    case 'unknown_session':
+      return 'Разговор уже закрыт';
+    case 'session_exists':
+      return 'Разговор по этому приглашению уже открыт';
+    case 'not_verified':
+      return 'Сначала подтвердите отпечаток';
+    case 'nonce_exhausted':
+      return 'Обновляем ключи, слишком много сообщений в очереди';
+    case 'timeout':
+      return 'Превышено время ожидания';
+    default:
+      return fallback;
+  }
+}
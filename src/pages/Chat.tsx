@@ -1,4 +1,4 @@
-import {useState, useEffect, useRef, FormEvent} from 'react';
+import {useState, useEffect, useRef, FormEvent, ReactNode} from 'react';
 import {ArrowLeft, Send, Shield} from 'lucide-react';
 import {Button} from '@/components/ui/button';
 import {Input} from '@/components/ui/input';
@@ -6,6 +6,8 @@ import {toast} from 'sonner';
 import {invoke} from '@tauri-apps/api/core';
 import {listen, UnlistenFn} from '@tauri-apps/api/event';
 import {useWindowSize} from 'react-use';
+import {describeError} from '@/lib/sscError';
+import {isForSession, SendStatus} from '@/lib/session';
 
 import {Message, ConnectionStatus} from '@/components/chat/types';
 import {MessageBubble} from '@/components/chat/MessageBubble';
@@ -13,10 +15,12 @@ import ShinyText from '@/components/text/ShinyText';
 import { AnimatePresence, motion } from 'framer-motion';
 
 interface ChatProps {
+  sessionId: string;
   onBack: () => void;
+  tabs?: ReactNode;
 }
 
-export default function Chat({onBack}: ChatProps) {
+export default function Chat({sessionId, onBack, tabs}: ChatProps) {
   /* ---------- constants ---------- */
   const MAX_MESSAGE_LENGTH = 1000; // Максимальная длина одного сообщения
 
@@ -84,13 +88,15 @@ export default function Chat({onBack}: ChatProps) {
 
   /* ---------- lifecycle ---------- */
   useEffect(() => {
-    // ↓ register all native listeners in one place
+    // ↓ register all native listeners in one place, only for this chat's session
     const register = (event: string, cb: Parameters<typeof listen>[1]) => {
-      listen(event, cb).then((un) => unlistenersRef.current.push(un));
+      listen(event, (e) => {
+        if (isForSession(e.payload, sessionId)) cb(e);
+      }).then((un) => unlistenersRef.current.push(un));
     };
 
     register('ssc-message', (e) => {
-      const txt = (e.payload as any).text ?? e.payload;
+      const txt = (e.payload as any).text;
       
       // Проверяем, является ли это частью длинного сообщения
       // Для простоты считаем, что если сообщение короткое, то это отдельное сообщение
@@ -126,6 +132,16 @@ export default function Chat({onBack}: ChatProps) {
       setStatus('connected');
       statusRef.current = 'connected';
     });
+    register('ssc-identity-changed', (e) => {
+      const { previous_fingerprint, new_fingerprint } = e.payload as any;
+      console.warn('ssc-identity-changed:', previous_fingerprint, '->', new_fingerprint);
+      toast.error('Ключ идентичности собеседника изменился! Сверьте отпечатки заново', {
+        duration: Infinity,
+      });
+    });
+    register('ssc-rekey-failed', () => {
+      toast.error('Собеседник не ответил на обновление ключей, разговор закрыт');
+    });
     register('ssc-connection-problem', () => {
       setStatus('problem');
       statusRef.current = 'problem';
@@ -227,7 +243,7 @@ export default function Chat({onBack}: ChatProps) {
       unlistenersRef.current.forEach((un) => un());
       if (clearHistoryTimeoutRef.current) clearTimeout(clearHistoryTimeoutRef.current);
     };
-  }, []);
+  }, [sessionId]);
 
   /* scroll down on new messages */
   useEffect(() => {
@@ -244,9 +260,9 @@ export default function Chat({onBack}: ChatProps) {
   /* ---------- actions ---------- */
   const handleBack = async () => {
     try {
-      await invoke('disconnect');
+      await invoke('close_session', {sessionId});
     } catch (e) {
-      console.error('disconnect error', e);
+      console.error('close_session error', e);
     }
     onBack();
   };
@@ -284,17 +300,21 @@ export default function Chat({onBack}: ChatProps) {
 
     try {
       // Отправляем каждую часть отдельно
+      let queued = false;
       for (let i = 0; i < messageParts.length; i++) {
-        const ok = await invoke<boolean>('send_text', {msg: messageParts[i]});
-        if (!ok) throw new Error(`send_text returned false for part ${i}`);
+        const sent = await invoke<SendStatus>('send_text', {sessionId, text: messageParts[i]});
+        queued ||= sent === 'queued';
         
         // Небольшая задержка между отправками частей
         if (i < messageParts.length - 1) {
           await new Promise(resolve => setTimeout(resolve, 100));
         }
       }
+      if (queued) {
+        toast.info('Сообщение уйдёт после обновления ключей');
+      }
     } catch (err) {
-      toast.error('Не удалось отправить сообщение');
+      toast.error(describeError(err, 'Не удалось отправить сообщение'));
       // Удаляем все части сообщения при ошибке
       setMessages((p) => p.filter((m) => !localMessages.some(lm => lm.id === m.id)));
     } finally {
@@ -372,6 +392,8 @@ export default function Chat({onBack}: ChatProps) {
         </div>
       </header>
 
+      {tabs}
+
       {/* Status banners - фиксированные баннеры */}
       {['problem', 'recovering', 'disconnected'].includes(status) && (
         <div
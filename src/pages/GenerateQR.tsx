@@ -2,7 +2,7 @@ import { useState, useEffect, useRef } from 'react';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Input } from '@/components/ui/input';
-import { QrCode as QrCodeIcon, Copy, Check, ArrowLeft, Scan, Download, X } from 'lucide-react';
+import { QrCode as QrCodeIcon, Copy, Check, ArrowLeft, Scan, Download, X, KeyRound } from 'lucide-react';
 import { toast } from 'sonner';
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
@@ -11,10 +11,12 @@ import { QRCodeCanvas } from 'qrcode.react';
 import FadeContent from '@/components/FadeContent';
 import Counter from '@/components/Counter';
 import CircularText from '@/components/CircularText';
+import { describeError } from '@/lib/sscError';
+import { describeIncompatible, IncompatiblePeer, SignalingBundle } from '@/lib/session';
 
 interface GenerateQRProps {
   onBack: () => void;
-  onConnected: () => void;
+  onConnected: (sessionId: string) => void;
   autoGenerate?: boolean;
   ttl?: number; // TTL в минутах
 }
@@ -29,16 +31,45 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
   const TTL = ttlMinutes * 60;
   const [ttl, setTtl] = useState(TTL);
   const timerRef = useRef<NodeJS.Timeout | null>(null);
+  // Сессия текущего offer; ref, чтобы слушатель видел актуальное значение
+  const sessionIdRef = useRef<string | null>(null);
+  const connectedRef = useRef(false);
+  // Пароль CPace; ref - чтобы перегенерация по таймеру брала текущее значение
+  const [passphrase, setPassphrase] = useState('');
+  const passphraseRef = useRef('');
+  // Шифровать ли offer одноразовым кодом переноса, и код текущего offer
+  const [useTransportCode, setUseTransportCode] = useState(false);
+  const useTransportCodeRef = useRef(false);
+  const [transportCode, setTransportCode] = useState<string | null>(null);
 
-  // Слушаем событие успешного подключения
+  // Слушаем событие успешного подключения нашей сессии
   useEffect(() => {
-    const un = listen("ssc-connected", () => {
+    const un = listen<{ session_id: string }>("ssc-connected", (e) => {
+      if (e.payload.session_id !== sessionIdRef.current) return;
       console.log('GenerateQR: received ssc-connected event');
-      onConnected();
+      connectedRef.current = true;
+      onConnected(e.payload.session_id);
     });
     return () => { un.then(f => f()); };
   }, [onConnected]);
 
+  // Собеседник не договорился с нами о протоколе: соединения не будет
+  useEffect(() => {
+    const un = listen<IncompatiblePeer>("ssc-incompatible-peer", (e) => {
+      if (e.payload.session_id !== sessionIdRef.current) return;
+      console.log('GenerateQR: received ssc-incompatible-peer event', e.payload);
+      toast.error(describeIncompatible(e.payload), { duration: Infinity });
+    });
+    return () => { un.then(f => f()); };
+  }, []);
+
+  // Неподключённую сессию закрываем при уходе со страницы
+  useEffect(() => () => {
+    if (sessionIdRef.current && !connectedRef.current) {
+      invoke('close_session', { sessionId: sessionIdRef.current }).catch(() => {});
+    }
+  }, []);
+
   // Генерируем QR-код автоматически при открытии страницы
   useEffect(() => {
     if (autoGenerate && !offer && !loading) {
@@ -74,12 +105,27 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
   const generateOffer = async () => {
     setLoading(true);
     try {
-      const result = await invoke('generate_offer_with_candidates') as string;
-      setOffer(result);
+      const secret = passphraseRef.current.trim();
+      // Для каждого offer - новый код: код уже переданного offer не откроет следующий
+      const code = useTransportCodeRef.current
+        ? await invoke<string>('generate_transport_code')
+        : null;
+      const result = await invoke('generate_offer_with_candidates', {
+        passphrase: secret || null,
+        transportCode: code,
+      }) as SignalingBundle;
+      // Прежний offer больше не нужен: закрываем его сессию
+      const previous = sessionIdRef.current;
+      sessionIdRef.current = result.session_id;
+      if (previous) {
+        invoke('close_session', { sessionId: previous }).catch(() => {});
+      }
+      setOffer(result.encoded);
+      setTransportCode(code);
       setAwaitingAnswer(true);
       toast.success('QR-код сгенерирован!');
     } catch (error) {
-      toast.error('Ошибка генерации QR-кода');
+      toast.error(describeError(error, 'Ошибка генерации QR-кода'));
       console.error('Error generating offer:', error);
     } finally {
       setLoading(false);
@@ -105,7 +151,10 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
 
     setLoading(true);
     try {
-      const success = await invoke('set_answer_with_candidates', { encoded: answer }) as boolean;
+      const success = await invoke('set_answer_with_candidates', {
+        sessionId: sessionIdRef.current,
+        encoded: answer,
+      }) as boolean;
       if (success) {
         toast.success('Соединение установлено!');
         // Не вызываем onConnected() здесь, событие ssc-connected сделает это автоматически
@@ -113,7 +162,7 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
         toast.error('Не удалось установить соединение');
       }
     } catch (error) {
-      toast.error('Ошибка при установке соединения');
+      toast.error(describeError(error, 'Ошибка при установке соединения'));
       console.error('Error setting answer:', error);
     } finally {
       setLoading(false);
@@ -130,6 +179,54 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
           <h1 className="text-2xl font-bold text-white">Создать подключение</h1>
         </div>
 
+        <Card className="bg-slate-800/50 border-slate-700">
+          <CardHeader>
+            <CardTitle className="text-white flex items-center space-x-2">
+              <KeyRound className="w-5 h-5" />
+              <span>Пароль</span>
+            </CardTitle>
+          </CardHeader>
+          <CardContent className="space-y-3">
+            <div className="flex space-x-2">
+              <Input
+                type="password"
+                placeholder="Необязательно"
+                value={passphrase}
+                onChange={(e) => {
+                  setPassphrase(e.target.value);
+                  passphraseRef.current = e.target.value;
+                }}
+                className="bg-slate-700 border-slate-600 text-white"
+              />
+              <Button
+                onClick={generateOffer}
+                disabled={loading}
+                className="bg-slate-600 hover:bg-slate-500"
+              >
+                Обновить QR
+              </Button>
+            </div>
+            <p className="text-slate-400 text-xs">
+              Ответить на приглашение сможет только знающий пароль. Сообщите его собеседнику
+              голосом или при встрече, а не вместе с QR-кодом.
+            </p>
+            <label className="flex items-center space-x-3 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={useTransportCode}
+                onChange={(e) => {
+                  setUseTransportCode(e.target.checked);
+                  useTransportCodeRef.current = e.target.checked;
+                }}
+                className="w-4 h-4 text-emerald-500 bg-slate-700 border-slate-600 rounded focus:ring-emerald-500"
+              />
+              <span className="text-white text-sm">
+                Зашифровать приглашение кодом переноса
+              </span>
+            </label>
+          </CardContent>
+        </Card>
+
         <Card className="bg-slate-800/50 border-slate-700">
           <CardHeader>
             <CardTitle className="text-white flex items-center space-x-2">
@@ -179,6 +276,16 @@ const GenerateQR = ({ onBack, onConnected, autoGenerate, ttl: ttlMinutes = 5 }:
                     </div>
                   </div>
                 </div>
+                {transportCode && (
+                  <div className="bg-slate-700 p-3 rounded border border-emerald-600 text-center space-y-1">
+                    <p className="text-slate-300 text-xs">Код переноса</p>
+                    <p className="text-white text-lg font-mono tracking-widest">{transportCode}</p>
+                    <p className="text-slate-400 text-xs">
+                      Без него приглашение не открыть. Передайте код отдельно от QR-кода,
+                      ответ собеседника будет зашифрован им же.
+                    </p>
+                  </div>
+                )}
                 <div className="flex items-center mt-2">
                   <span className="text-slate-300 mr-2">Сохранить QR</span>
                   <Button
@@ -6,6 +6,8 @@ import Chat from './Chat';
 import VerifyFingerprint from './VerifyFingerprint';
 import GradientText from '@/components/text/GradientText';
 import Settings from './Settings';
+import { invoke } from '@tauri-apps/api/core';
+import { SessionTabs } from '@/components/chat/SessionTabs';
 
 type AppMode = 'welcome' | 'generate' | 'scan' | 'verify' | 'chat' | 'settings';
 
@@ -13,6 +15,9 @@ const Index = () => {
   const [mode, setMode] = useState<AppMode>('welcome');
   const [showOptions, setShowOptions] = useState(false);
   const [ttl, setTtl] = useState(5); // TTL по умолчанию 5 минут
+  const [sessionId, setSessionId] = useState<string | null>(null);
+  // Сессии, дошедшие до чата; их Chat остаются смонтированными, чтобы не терять сообщения
+  const [chats, setChats] = useState<string[]>([]);
 
   // Загружаем TTL из настроек при монтировании
   useEffect(() => {
@@ -42,6 +47,10 @@ const Index = () => {
     }
     // Обновляем TTL при возврате (на случай если пользователь изменил настройки)
     loadTTL();
+    // Есть открытые чаты - возвращаемся к ним
+    if (chats.length > 0) {
+      switchTo(sessionId && chats.includes(sessionId) ? sessionId : chats[chats.length - 1]);
+    }
   };
 
   const handleSettings = () => {
@@ -69,80 +78,139 @@ const Index = () => {
     setMode('generate');
   };
 
-  const handleConnected = () => {
+  const handleConnected = (id: string) => {
     console.log('Index: handleConnected called - switching to verify mode');
+    setSessionId(id);
     setMode('verify');
   };
 
-  if (mode === 'settings') {
-    return <Settings onBack={handleBack} />;
-  }
+  const switchTo = (id: string) => {
+    invoke('switch_session', { sessionId: id }).catch((e) => console.error('switch_session error', e));
+    setSessionId(id);
+    setMode('chat');
+  };
 
-  if (mode === 'chat') {
-    return <Chat onBack={() => setMode('welcome')} />;
-  }
+  const handleVerified = (id: string) => {
+    setChats((prev) => (prev.includes(id) ? prev : [...prev, id]));
+    switchTo(id);
+  };
 
-  if (mode === 'verify') {
-    return <VerifyFingerprint 
-      onConfirm={() => setMode('chat')} 
-      onCancel={() => setMode('welcome')} 
-    />;
-  }
+  // Чат закрыт: переходим к другому открытому чату или на стартовый экран
+  const handleChatClosed = (id: string) => {
+    const rest = chats.filter((c) => c !== id);
+    setChats(rest);
+    if (id !== sessionId) return;
+    if (rest.length > 0) {
+      switchTo(rest[rest.length - 1]);
+    } else {
+      setSessionId(null);
+      setMode('welcome');
+    }
+  };
 
-  if (mode === 'generate') {
-    return <GenerateQR onBack={handleBack} onConnected={handleConnected} autoGenerate={true} ttl={ttl} />;
-  }
+  const handleCloseTab = (id: string) => {
+    invoke('close_session', { sessionId: id }).catch((e) => console.error('close_session error', e));
+    handleChatClosed(id);
+  };
 
-  if (mode === 'scan') {
-    return <ScanQR onBack={handleBack} onConnected={handleConnected} />;
-  }
+  const handleNewChat = () => {
+    setShowOptions(true);
+    setMode('welcome');
+  };
+
+  const chatViews = chats.map((id) => (
+    <div key={id} className={mode === 'chat' && id === sessionId ? '' : 'hidden'}>
+      <Chat
+        sessionId={id}
+        onBack={() => handleChatClosed(id)}
+        tabs={
+          <SessionTabs
+            activeId={id}
+            chatIds={chats}
+            onSwitch={switchTo}
+            onClose={handleCloseTab}
+            onNew={handleNewChat}
+          />
+        }
+      />
+    </div>
+  ));
+
+  return (
+    <>
+      {chatViews}
+      {mode !== 'chat' && renderScreen()}
+    </>
+  );
+
+  function renderScreen() {
+    if (mode === 'settings') {
+      return <Settings onBack={handleBack} />;
+    }
+
+    if (mode === 'verify' && sessionId) {
+      return <VerifyFingerprint 
+        sessionId={sessionId}
+        onConfirm={() => handleVerified(sessionId)} 
+        onCancel={() => (chats.length > 0 ? switchTo(chats[chats.length - 1]) : setMode('welcome'))} 
+      />;
+    }
+
+    if (mode === 'generate') {
+      return <GenerateQR onBack={handleBack} onConnected={handleConnected} autoGenerate={true} ttl={ttl} />;
+    }
 
-  if (showOptions) {
-    return (
-      <div className="min-h-screen bg-gradient-to-br from-slate-900 via-slate-800 to-slate-900 flex items-center justify-center p-4">
-        <div className="max-w-md w-full space-y-6">
-          <div className="text-center">
-            <h2 className="text-3xl font-bold text-white mb-4">
-              <GradientText
-                colors={['#40ffaa', '#4079ff', '#40ffaa', '#4079ff', '#40ffaa']}
-                animationSpeed={5}
+    if (mode === 'scan') {
+      return <ScanQR onBack={handleBack} onConnected={handleConnected} />;
+    }
+
+    if (showOptions) {
+      return (
+        <div className="min-h-screen bg-gradient-to-br from-slate-900 via-slate-800 to-slate-900 flex items-center justify-center p-4">
+          <div className="max-w-md w-full space-y-6">
+            <div className="text-center">
+              <h2 className="text-3xl font-bold text-white mb-4">
+                <GradientText
+                  colors={['#40ffaa', '#4079ff', '#40ffaa', '#4079ff', '#40ffaa']}
+                  animationSpeed={5}
+                >
+                  Выберите действие
+                </GradientText>
+              </h2>
+              <p className="text-slate-300">Создайте новое подключение или присоединитесь к существующему</p>
+            </div>
+
+            <div className="space-y-4">
+              <button
+                onClick={handleGenerate}
+                className="w-full p-6 bg-slate-800/50 hover:bg-slate-700/50 border border-slate-700 rounded-lg transition-all text-left"
               >
-                Выберите действие
-              </GradientText>
-            </h2>
-            <p className="text-slate-300">Создайте новое подключение или присоединитесь к существующему</p>
-          </div>
+                <h3 className="text-white font-semibold mb-2">Создать QR-код</h3>
+                <p className="text-slate-400 text-sm">Создайте новое подключение и поделитесь QR-кодом</p>
+              </button>
 
-          <div className="space-y-4">
-            <button
-              onClick={handleGenerate}
-              className="w-full p-6 bg-slate-800/50 hover:bg-slate-700/50 border border-slate-700 rounded-lg transition-all text-left"
-            >
-              <h3 className="text-white font-semibold mb-2">Создать QR-код</h3>
-              <p className="text-slate-400 text-sm">Создайте новое подключение и поделитесь QR-кодом</p>
-            </button>
+              <button
+                onClick={() => setMode('scan')}
+                className="w-full p-6 bg-slate-800/50 hover:bg-slate-700/50 border border-slate-700 rounded-lg transition-all text-left"
+              >
+                <h3 className="text-white font-semibold mb-2">Сканировать QR-код</h3>
+                <p className="text-slate-400 text-sm">Присоединитесь к существующему подключению</p>
+              </button>
+            </div>
 
             <button
-              onClick={() => setMode('scan')}
-              className="w-full p-6 bg-slate-800/50 hover:bg-slate-700/50 border border-slate-700 rounded-lg transition-all text-left"
+              onClick={handleBack}
+              className="w-full text-slate-400 hover:text-white transition-colors"
             >
-              <h3 className="text-white font-semibold mb-2">Сканировать QR-код</h3>
-              <p className="text-slate-400 text-sm">Присоединитесь к существующему подключению</p>
+              ← Назад
             </button>
           </div>
-
-          <button
-            onClick={handleBack}
-            className="w-full text-slate-400 hover:text-white transition-colors"
-          >
-            ← Назад
-          </button>
         </div>
-      </div>
-    );
-  }
+      );
+    }
 
-  return <Welcome onStart={handleStart} onSettings={handleSettings} />;
+    return <Welcome onStart={handleStart} onSettings={handleSettings} />;
+  }
 };
 
 export default Index;
\ No newline at end of file
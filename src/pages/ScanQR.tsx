@@ -1,17 +1,20 @@
-import { useState, useEffect } from 'react';
+import { useState, useEffect, useRef } from 'react';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Textarea } from '@/components/ui/textarea';
+import { Input } from '@/components/ui/input';
 import { Scan, ArrowLeft, Link, Copy, Check, Download } from 'lucide-react';
 import { toast } from 'sonner';
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import jsQR from 'jsqr';
 import { QRCodeSVG, QRCodeCanvas } from 'qrcode.react';
+import { describeError } from '@/lib/sscError';
+import { describeIncompatible, IncompatiblePeer, SignalingBundle } from '@/lib/session';
 
 interface ScanQRProps {
   onBack: () => void;
-  onConnected: () => void;
+  onConnected: (sessionId: string) => void;
 }
 
 const ScanQR = ({ onBack, onConnected }: ScanQRProps) => {
@@ -20,16 +23,39 @@ const ScanQR = ({ onBack, onConnected }: ScanQRProps) => {
   const [loading, setLoading] = useState(false);
   const [copied, setCopied] = useState(false);
   const [fileLoading, setFileLoading] = useState(false);
+  const [passphrase, setPassphrase] = useState('');
+  const [transportCode, setTransportCode] = useState('');
+  const sessionIdRef = useRef<string | null>(null);
+  const connectedRef = useRef(false);
 
-  // Слушаем событие успешного подключения
+  // Слушаем событие успешного подключения нашей сессии
   useEffect(() => {
-    const un = listen("ssc-connected", () => {
+    const un = listen<{ session_id: string }>("ssc-connected", (e) => {
+      if (e.payload.session_id !== sessionIdRef.current) return;
       console.log('ScanQR: received ssc-connected event');
-      onConnected();
+      connectedRef.current = true;
+      onConnected(e.payload.session_id);
     });
     return () => { un.then(f => f()); };
   }, [onConnected]);
 
+  // Собеседник не договорился с нами о протоколе: соединения не будет
+  useEffect(() => {
+    const un = listen<IncompatiblePeer>("ssc-incompatible-peer", (e) => {
+      if (e.payload.session_id !== sessionIdRef.current) return;
+      console.log('ScanQR: received ssc-incompatible-peer event', e.payload);
+      toast.error(describeIncompatible(e.payload), { duration: Infinity });
+    });
+    return () => { un.then(f => f()); };
+  }, []);
+
+  // Неподключённую сессию закрываем при уходе со страницы
+  useEffect(() => () => {
+    if (sessionIdRef.current && !connectedRef.current) {
+      invoke('close_session', { sessionId: sessionIdRef.current }).catch(() => {});
+    }
+  }, []);
+
   const handleAcceptOffer = async () => {
     if (!offerInput.trim()) {
       toast.error('Введите ссылку или данные QR-кода');
@@ -38,11 +64,18 @@ const ScanQR = ({ onBack, onConnected }: ScanQRProps) => {
 
     setLoading(true);
     try {
-      const result = await invoke('accept_offer_with_candidates', { encoded: offerInput }) as string;
-      setAnswer(result);
+      const secret = passphrase.trim();
+      const code = transportCode.trim();
+      const result = await invoke('accept_offer_with_candidates', {
+        encoded: offerInput,
+        passphrase: secret || null,
+        transportCode: code || null,
+      }) as SignalingBundle;
+      sessionIdRef.current = result.session_id;
+      setAnswer(result.encoded);
       toast.success('Ответ сгенерирован! Отправьте его собеседнику.');
     } catch (error) {
-      toast.error('Ошибка при обработке предложения');
+      toast.error(describeError(error, 'Ошибка при обработке предложения'));
       console.error('Error accepting offer:', error);
     } finally {
       setLoading(false);
@@ -154,6 +187,19 @@ const ScanQR = ({ onBack, onConnected }: ScanQRProps) => {
               onChange={(e) => setOfferInput(e.target.value)}
               className="bg-slate-700 border-slate-600 text-white min-h-[100px]"
             />
+            <Input
+              type="password"
+              placeholder="Пароль, если собеседник его задал"
+              value={passphrase}
+              onChange={(e) => setPassphrase(e.target.value)}
+              className="bg-slate-700 border-slate-600 text-white"
+            />
+            <Input
+              placeholder="Код переноса, если он показан рядом с QR-кодом"
+              value={transportCode}
+              onChange={(e) => setTransportCode(e.target.value)}
+              className="bg-slate-700 border-slate-600 text-white font-mono"
+            />
             <Button 
               onClick={handleAcceptOffer}
               disabled={loading || !offerInput.trim()}
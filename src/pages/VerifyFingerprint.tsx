@@ -1,20 +1,40 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { Button } from '@/components/ui/button';
 import { Card, CardContent } from '@/components/ui/card';
+import { ToggleGroup, ToggleGroupItem } from '@/components/ui/toggle-group';
 import { Shield, ArrowLeft, Loader2 } from 'lucide-react';
 import { invoke } from "@tauri-apps/api/core";
 import DecryptedText from "@/components/text/DecryptedText";
+import type { SasFormat } from '@/lib/session';
+
+// Форматы кода сверки; выбранный запоминается между сессиями
+const SAS_FORMATS: { value: SasFormat; label: string }[] = [
+  { value: 'hex', label: 'Hex' },
+  { value: 'words', label: 'Слова' },
+  { value: 'emoji', label: 'Эмодзи' },
+  { value: 'numeric', label: 'Цифры' },
+];
+const SAS_FORMAT_KEY = 'ssc-sas-format';
+
+const savedFormat = (): SasFormat => {
+  const saved = localStorage.getItem(SAS_FORMAT_KEY);
+  return SAS_FORMATS.find(f => f.value === saved)?.value ?? 'hex';
+};
 
 interface VerifyFingerprintProps {
+  sessionId: string;
   onConfirm: () => void;
   onCancel: () => void;
 }
 
-const VerifyFingerprint = ({ onConfirm, onCancel }: VerifyFingerprintProps) => {
+const VerifyFingerprint = ({ sessionId, onConfirm, onCancel }: VerifyFingerprintProps) => {
   const [fingerprint, setFingerprint] = useState<string | null>(null);
   const [isLoading, setIsLoading] = useState(true);
   const [checked, setChecked] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [format, setFormat] = useState<SasFormat>(savedFormat);
+  // ref, чтобы попытки получения кода брали текущий формат
+  const formatRef = useRef(format);
 
   console.log('VerifyFingerprint: Component mounted/rendered');
 
@@ -28,7 +48,7 @@ const VerifyFingerprint = ({ onConfirm, onCancel }: VerifyFingerprintProps) => {
         await new Promise(resolve => setTimeout(resolve, 500));
         
         // Сначала проверяем соединение
-        const connected = await invoke<boolean>("is_connected");
+        const connected = await invoke<boolean>("is_connected", { sessionId });
         
         if (!connected) {
           setError("Соединение не установлено");
@@ -42,7 +62,7 @@ const VerifyFingerprint = ({ onConfirm, onCancel }: VerifyFingerprintProps) => {
         
         while (attempts < maxAttempts && !fp) {
           try {
-            fp = await invoke<string>("get_fingerprint");
+            fp = await invoke<string | null>("get_fingerprint_as", { sessionId, format: formatRef.current });
             console.log(`VerifyFingerprint: Attempt ${attempts + 1}, fingerprint:`, fp);
             if (fp && fp.trim() !== "") {
               console.log('VerifyFingerprint: Successfully got fingerprint:', fp);
@@ -69,17 +89,37 @@ const VerifyFingerprint = ({ onConfirm, onCancel }: VerifyFingerprintProps) => {
     };
 
     getFingerprint();
-  }, []);
+  }, [sessionId]);
+
+  const handleFormatChange = async (value: string) => {
+    // Повторный клик по выбранному формату снимает выбор - оставляем прежний
+    const next = SAS_FORMATS.find(f => f.value === value)?.value;
+    if (!next) return;
+    setFormat(next);
+    formatRef.current = next;
+    localStorage.setItem(SAS_FORMAT_KEY, next);
+    try {
+      const fp = await invoke<string | null>("get_fingerprint_as", { sessionId, format: next });
+      if (fp) setFingerprint(fp);
+    } catch (err) {
+      console.warn('VerifyFingerprint: Failed to get fingerprint as', next, err);
+    }
+  };
 
-  const handleConfirm = () => {
+  const handleConfirm = async () => {
     if (fingerprint && checked) {
+      try {
+        await invoke('mark_fingerprint_verified', { sessionId });
+      } catch (error) {
+        console.error('Ошибка подтверждения отпечатка:', error);
+      }
       onConfirm();
     }
   };
 
   const handleCancel = async () => {
     try {
-      await invoke('disconnect');
+      await invoke('reject_fingerprint', { sessionId });
     } catch (error) {
       console.error('Ошибка отключения:', error);
     }
@@ -119,8 +159,25 @@ const VerifyFingerprint = ({ onConfirm, onCancel }: VerifyFingerprintProps) => {
               </div>
             ) : (
               <div className="text-center">
+                <ToggleGroup
+                  type="single"
+                  size="sm"
+                  value={format}
+                  onValueChange={handleFormatChange}
+                  className="mb-4 flex-wrap"
+                >
+                  {SAS_FORMATS.map(({ value, label }) => (
+                    <ToggleGroupItem
+                      key={value}
+                      value={value}
+                      className="text-slate-300 data-[state=on]:bg-slate-700 data-[state=on]:text-white"
+                    >
+                      {label}
+                    </ToggleGroupItem>
+                  ))}
+                </ToggleGroup>
                 <div className="bg-slate-700/50 rounded-lg p-4 mb-4">
-                  <p className="text-white font-mono text-lg font-bold tracking-wider">
+                  <p className="text-white font-mono text-lg font-bold tracking-wider break-words">
                     <DecryptedText
                       text={fingerprint}
                       animateOn="view"